@@ -0,0 +1,102 @@
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use connect_four_rs::board::Board;
+use connect_four_rs::engine::Engine;
+use connect_four_rs::game::Game;
+use connect_four_rs::player::Player;
+use connect_four_rs::variant::Variant;
+use connect_four_rs::win_condition_strategy::WinConditionStrategy;
+
+/// A reachable midgame position without a winner.
+const MIDGAME: &str = "332445";
+
+
+fn midgame_board() -> Board {
+    let mut board = Board::new();
+    board.play_moves(MIDGAME, &Game::generate_simulation_players()).unwrap();
+    board
+}
+
+
+fn board_operations(c: &mut Criterion) {
+    let board = midgame_board();
+    let mut group = c.benchmark_group("board");
+    group.bench_function("is_full", |b| b.iter(|| black_box(&board).is_full()));
+    group.bench_function("is_valid_move", |b| b.iter(|| black_box(&board).is_valid_move(black_box(3))));
+    group.bench_function("apply_gravity", |b| b.iter(|| black_box(&board).apply_gravity(black_box(3))));
+    group.bench_function("drop_and_undo", |b| {
+        let mut board = board.clone();
+        let player = Game::generate_simulation_players()[0].clone();
+        b.iter(|| {
+            board.drop_piece(black_box(0), &player);
+            board.undo_move(black_box(0))
+        })
+    });
+    group.finish();
+}
+
+
+fn win_condition_strategies(c: &mut Criterion) {
+    let board = midgame_board();
+    // collecting discs only counts in Pop 10
+    let mut pop10_board = board.clone();
+    pop10_board.set_variant(Variant::Pop10);
+    let player = Game::generate_simulation_players()[0].clone();
+    let mut group = c.benchmark_group("win_condition_strategy");
+    for strategy in Game::setup_win_condition_strategies() {
+        let board = if matches!(strategy, WinConditionStrategy::CollectionWinStrategy) { &pop10_board } else { &board };
+        group.bench_function(format!("{:?}", strategy), |b| {
+            b.iter(|| strategy.has_won(black_box(&player), black_box(board)))
+        });
+    }
+    group.finish();
+}
+
+
+fn random_playout(players: &[Player], strategies: &[WinConditionStrategy]) -> u8 {
+    let mut board = Board::new();
+    loop {
+        let player = &players[board.disc_count() as usize % players.len()];
+        let Ok(mv) = player.generate_move(&board) else {
+            return board.disc_count();
+        };
+        board.play(mv, player);
+        if strategies.iter().any(|strategy| strategy.has_won(player, &board)) {
+            return board.disc_count();
+        }
+    }
+}
+
+
+fn playouts(c: &mut Criterion) {
+    let players = Game::generate_simulation_players();
+    let strategies = Game::setup_win_condition_strategies();
+    c.bench_function("random_playout", |b| b.iter(|| random_playout(&players, &strategies)));
+}
+
+
+fn search(c: &mut Criterion) {
+    let board = midgame_board();
+    let players = Game::generate_simulation_players();
+    let player_index = board.disc_count() as usize % players.len();
+    let mut group = c.benchmark_group("engine");
+    group.sample_size(10);
+    for depth in [4, 6] {
+        let nodes = Engine::new(depth).search(&board, &players, player_index).nodes;
+        group.throughput(Throughput::Elements(nodes));
+        group.bench_function(format!("search_depth_{}", depth), |b| {
+            // a fresh engine per run, so its table starts empty without timing the allocation
+            b.iter_batched(
+                || Engine::new(depth),
+                |mut engine| engine.search(black_box(&board), &players, player_index),
+                BatchSize::LargeInput
+            )
+        });
+    }
+    group.finish();
+}
+
+
+criterion_group!(benches, board_operations, win_condition_strategies, playouts, search);
+criterion_main!(benches);
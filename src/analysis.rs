@@ -0,0 +1,306 @@
+use std::fmt;
+
+use crate::engine::is_decisive;
+use crate::events::{GameEvent, GameObserver};
+use crate::game::Game;
+use crate::player::Player;
+use crate::replay::Replay;
+use crate::variant::Move;
+
+pub const ANALYSIS_DEPTH: u8 = 6;
+// score lost compared to the engine's move, in `evaluate` units
+const INACCURACY_LOSS: i16 = 4;
+const MISTAKE_LOSS: i16 = 15;
+const BLUNDER_LOSS: i16 = 40;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum MoveQuality {
+    Best,
+    Inaccuracy,
+    Mistake,
+    Blunder
+}
+
+/// Result of a position for the player to move, as far as the engine can see.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Outcome {
+    Loss,
+    Unclear,
+    Draw,
+    Win
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MoveAnalysis {
+    pub ply: usize,
+    pub player: Player,
+    pub played: Move,
+    pub best_move: Option<Move>,
+    pub score_before: i16, // the mover's point of view
+    pub score_after: i16,
+    pub outcome_before: Outcome,
+    pub outcome_after: Outcome,
+    pub quality: MoveQuality
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlayerSummary {
+    pub player: Player,
+    pub best: usize,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+    pub accuracy: u8 // percent
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub moves: Vec<MoveAnalysis>,
+    players: Vec<Player>
+}
+
+
+impl Outcome {
+    fn of(score: i16, finished_draw: bool) -> Self {
+        if is_decisive(score) {
+            if score > 0 { Outcome::Win } else { Outcome::Loss }
+        } else if finished_draw {
+            Outcome::Draw
+        } else {
+            Outcome::Unclear
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Outcome::Loss => 0,
+            Outcome::Unclear | Outcome::Draw => 1,
+            Outcome::Win => 2
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Loss => write!(f, "loss"),
+            Outcome::Unclear => write!(f, "unclear"),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Win => write!(f, "win")
+        }
+    }
+}
+
+impl fmt::Display for MoveQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveQuality::Best => write!(f, "best"),
+            MoveQuality::Inaccuracy => write!(f, "inaccuracy"),
+            MoveQuality::Mistake => write!(f, "mistake"),
+            MoveQuality::Blunder => write!(f, "blunder")
+        }
+    }
+}
+
+
+impl MoveQuality {
+    /// Worsening the result is always a blunder, otherwise the lost score decides.
+    pub fn classify(played: Option<Move>, best: Option<Move>, before: (i16, Outcome), after: (i16, Outcome)) -> Self {
+        if after.1.rank() < before.1.rank() {
+            return MoveQuality::Blunder;
+        }
+        if played == best || before.1 != Outcome::Unclear {
+            return MoveQuality::Best;
+        }
+        match before.0.saturating_sub(after.0) {
+            loss if loss >= BLUNDER_LOSS => MoveQuality::Blunder,
+            loss if loss >= MISTAKE_LOSS => MoveQuality::Mistake,
+            loss if loss >= INACCURACY_LOSS => MoveQuality::Inaccuracy,
+            _ => MoveQuality::Best
+        }
+    }
+
+    /// Weight of the move in the accuracy percentage.
+    fn accuracy(&self) -> u32 {
+        match self {
+            MoveQuality::Best => 100,
+            MoveQuality::Inaccuracy => 70,
+            MoveQuality::Mistake => 40,
+            MoveQuality::Blunder => 0
+        }
+    }
+}
+
+
+impl Analysis {
+    /// Classifies every move of `replay` using its evaluations.
+    pub fn new(replay: &Replay) -> Self {
+        let players = replay.players().to_vec();
+        let evaluations = replay.evaluations();
+        let positions = replay.positions();
+        let moves = replay
+            .moves()
+            .iter()
+            .enumerate()
+            .map(|(ply, played)| {
+                let mover = replay.movers()[ply];
+                let sign = if mover == 0 { 1 } else { -1 };
+                let score_before = sign * evaluations[ply];
+                let score_after = sign * evaluations[ply + 1];
+                let outcome_before = Outcome::of(score_before, false);
+                // the opponent having no legal move draws, a full board alone does not when discs can be popped
+                let outcome_after = Outcome::of(score_after, positions[ply + 1].is_stuck(&players[1 - mover]));
+                MoveAnalysis {
+                    ply,
+                    player: players[mover].clone(),
+                    played: *played,
+                    best_move: replay.best_moves()[ply],
+                    score_before,
+                    score_after,
+                    outcome_before,
+                    outcome_after,
+                    quality: MoveQuality::classify(
+                        Some(*played),
+                        replay.best_moves()[ply],
+                        (score_before, outcome_before),
+                        (score_after, outcome_after)
+                    )
+                }
+            })
+            .collect();
+        Self { moves, players }
+    }
+
+    pub fn from_game(game: &Game, depth: u8) -> Result<Self, String> {
+        let mut start = game.board().clone();
+        start.clear();
+        let replay = Replay::new(start, game.players().to_vec(), game.moves().to_vec(), game.movers().to_vec(), depth)?;
+        Ok(Analysis::new(&replay))
+    }
+
+    pub fn summary(&self, player: &Player) -> PlayerSummary {
+        let moves: Vec<&MoveAnalysis> = self.moves.iter().filter(|analysis| analysis.player == *player).collect();
+        let count = |quality| moves.iter().filter(|analysis| analysis.quality == quality).count();
+        let accuracy = match moves.len() {
+            0 => 100,
+            n => moves.iter().map(|analysis| analysis.quality.accuracy()).sum::<u32>() / n as u32
+        };
+        PlayerSummary {
+            player: player.clone(),
+            best: count(MoveQuality::Best),
+            inaccuracies: count(MoveQuality::Inaccuracy),
+            mistakes: count(MoveQuality::Mistake),
+            blunders: count(MoveQuality::Blunder),
+            accuracy: accuracy as u8
+        }
+    }
+
+    /// Every move with its classification followed by a summary per player.
+    pub fn report(&self) -> String {
+        let mut out = String::from("Move analysis:\n");
+        for analysis in &self.moves {
+            let best = match analysis.best_move {
+                Some(best) if best != analysis.played && analysis.quality != MoveQuality::Best => format!(" (best {})", best),
+                _ => String::new()
+            };
+            out.push_str(&format!(
+                "{:>3}. {} {}  {:<10} {:>6} -> {:<6} {} -> {}{}\n",
+                analysis.ply + 1,
+                analysis.player,
+                analysis.played,
+                analysis.quality,
+                analysis.score_before,
+                analysis.score_after,
+                analysis.outcome_before,
+                analysis.outcome_after,
+                best
+            ));
+        }
+        for player in &self.players {
+            let summary = self.summary(player);
+            out.push_str(&format!(
+                "{} ({}): {} best, {} inaccuracies, {} mistakes, {} blunders, accuracy {}%\n",
+                player.name, player, summary.best, summary.inaccuracies, summary.mistakes, summary.blunders, summary.accuracy
+            ));
+        }
+        out
+    }
+}
+
+
+/// Prints an analysis of every game when it ends.
+#[derive(Debug, Clone, Copy)]
+pub struct AnalysisObserver {
+    pub depth: u8
+}
+
+impl Default for AnalysisObserver {
+    fn default() -> Self {
+        Self { depth: ANALYSIS_DEPTH }
+    }
+}
+
+impl GameObserver for AnalysisObserver {
+    fn notify(&mut self, game: &Game, event: &GameEvent) {
+        if !matches!(event, GameEvent::Won { .. } | GameEvent::Draw) {
+            return;
+        }
+        match Analysis::from_game(game, self.depth) {
+            Ok(analysis) => println!("{}", analysis.report()),
+            Err(e) => println!("Could not analyse the game: {}", e)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+
+    #[test]
+    fn classifies_moves() {
+        let unclear = |score| (score, Outcome::Unclear);
+        let (two, three) = (Some(Move::Drop(2)), Some(Move::Drop(3)));
+        assert_eq!(MoveQuality::classify(three, three, unclear(10), unclear(-30)), MoveQuality::Best);
+        assert_eq!(MoveQuality::classify(two, three, unclear(10), unclear(8)), MoveQuality::Best);
+        assert_eq!(MoveQuality::classify(two, three, unclear(10), unclear(0)), MoveQuality::Inaccuracy);
+        assert_eq!(MoveQuality::classify(two, three, unclear(10), unclear(-10)), MoveQuality::Mistake);
+        assert_eq!(MoveQuality::classify(two, three, unclear(10), unclear(-40)), MoveQuality::Blunder);
+        assert_eq!(MoveQuality::classify(two, three, unclear(10), (-9990, Outcome::Loss)), MoveQuality::Blunder);
+        assert_eq!(MoveQuality::classify(two, three, (9990, Outcome::Win), (9980, Outcome::Win)), MoveQuality::Best);
+    }
+
+    #[test]
+    fn finds_the_losing_blunder() {
+        let mut game = Game::create_simulated_game();
+        for col in [3, 3, 4, 4, 5, 5, 6] {
+            game.play_move(col).unwrap();
+        }
+        let analysis = Analysis::from_game(&game, 4).unwrap();
+        assert_eq!(analysis.moves.len(), 7);
+        let blunder = &analysis.moves[3];
+        assert_eq!((blunder.played, blunder.quality), (Move::Drop(4), MoveQuality::Blunder));
+        assert_eq!((blunder.outcome_before, blunder.outcome_after), (Outcome::Unclear, Outcome::Loss));
+        assert_eq!(analysis.moves[6].outcome_after, Outcome::Win);
+
+        let loser = analysis.summary(&game.players()[1]);
+        assert!(loser.blunders >= 1 && loser.accuracy < 100);
+        assert!(analysis.summary(&game.players()[0]).accuracy > loser.accuracy);
+        assert!(analysis.report().contains("henk-two (o):"));
+    }
+
+    #[test]
+    fn a_full_board_is_no_draw_while_discs_can_be_popped() {
+        let players = Game::generate_simulation_players();
+        let mut start = crate::board::Board::with_size(4, 4, 4).unwrap();
+        start.set_variant(crate::variant::Variant::PopOut);
+        for (row, cells) in ["112.", "2211", "1122", "2211"].iter().enumerate() {
+            for (col, cell) in cells.chars().enumerate().filter(|(_, cell)| *cell != '.') {
+                start.set_cell(row as u8, col as u8, &players[if cell == '1' { 0 } else { 1 }]);
+            }
+        }
+        let replay = Replay::new(start, players, vec![Move::Drop(3)], vec![1], 2).unwrap();
+        assert!(replay.positions()[1].is_full());
+        assert_ne!(Analysis::new(&replay).moves[0].outcome_after, Outcome::Draw);
+    }
+}
@@ -0,0 +1,9 @@
+use std::io::{stdin, stdout};
+
+use connect_four_rs::protocol::run_engine;
+
+
+/// Speaks the engine protocol on stdin/stdout, see `connect_four_rs::protocol`.
+fn main() -> Result<(), String> {
+    run_engine(stdin().lock(), stdout().lock())
+}
@@ -1,27 +1,524 @@
 use rayon::prelude::*;
 use std::fmt;
 use crate::player::Player;
-use crate::player::PlayerType;
+use crate::variant::{Move, Variant};
+use crate::win_condition_strategy::CONNECTED_COMPONENTS_WIN_THRESHOLD;
 
 const ROWS: u8 = 6;
 const COLS: u8 = 7;
+const MIN_SIZE: u8 = 4;
+const MAX_SIZE: u8 = 10; // columns are written as single digits
 
-#[derive(Debug)]
+/// Id the neutral blockers on obstacle boards are hashed with, no player can have it.
+const BLOCKER_ID: u8 = u8::MAX;
+
+/// What occupies a cell of the board.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Cell {
+    Empty,
+    Blocked, // a neutral blocker or hole, no disc can go there
+    Disc(Player)
+}
+
+impl Cell {
+    /// Id the cell is hashed with, nothing for an empty cell.
+    fn id(&self) -> u8 {
+        match self {
+            Cell::Empty => 0,
+            Cell::Blocked => BLOCKER_ID,
+            Cell::Disc(player) => player.id
+        }
+    }
+
+    /// Whether the cell holds a disc of `player`.
+    pub fn is_disc_of(&self, player: &Player) -> bool {
+        matches!(self, Cell::Disc(disc) if disc == player)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Board {
-    pub data: [[Player; COLS as usize]; ROWS as usize],
+    data: Vec<Vec<Cell>>, // data[0] is the top row
+    rows: u8,
+    cols: u8,
+    win_length: u8,
+    variant: Variant,
+    topology: Topology,
+    captured: Vec<u8>, // Pop 10 discs collected, indexed by player id
+    hash: u64,
+    mirrored_hash: u64,
+}
+
+
+/// Zobrist key for `player_id` occupying (`row`, `col`). Empty cells (id 0) contribute nothing,
+/// so the hash of an empty board is 0.
+pub fn zobrist_key(row: u8, col: u8, player_id: u8) -> u64 {
+    if player_id == 0 {
+        return 0;
+    }
+    // splitmix64 over the cell/player index gives well spread, deterministic keys
+    let mut z = ((row as u64) << 16 | (col as u64) << 8 | player_id as u64)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+
+/// Zobrist key for `player_id` having collected `count` discs, nothing for none.
+fn captured_key(player_id: u8, count: u8) -> u64 {
+    if count == 0 { 0 } else { zobrist_key(u8::MAX - 1, count, player_id) }
+}
+
+
+/// Cells written as `<row>:<col>` separated by commas, rows counted from the top, e.g. `5:3,4:0`. `-` is no cells.
+pub fn parse_cells(text: &str) -> Result<Vec<(u8, u8)>, String> {
+    let text = text.trim();
+    if text == "-" {
+        return Ok(vec![]);
+    }
+    text.split(',')
+        .map(|cell| {
+            let (row, col) = cell.split_once(':').ok_or(format!("Invalid cell \"{}\", expected <row>:<col>", cell))?;
+            let number = |n: &str| n.trim().parse::<u8>().map_err(|e| format!("Invalid cell \"{}\": {}", cell, e));
+            Ok((number(row)?, number(col)?))
+        })
+        .collect()
+}
+
+/// Cells in the notation `parse_cells` reads.
+pub fn format_cells(cells: &[(u8, u8)]) -> String {
+    if cells.is_empty() {
+        return String::from("-");
+    }
+    cells.iter().map(|(row, col)| format!("{}:{}", row, col)).collect::<Vec<_>>().join(",")
+}
+
+
+/// How the edges of the board connect.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Topology {
+    #[default]
+    Flat,
+    Cylinder // the first and last columns are neighbours, lines wrap around
+}
+
+impl Topology {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            "flat" => Ok(Topology::Flat),
+            "cylinder" => Ok(Topology::Cylinder),
+            other => Err(format!("Unknown topology \"{}\", expected flat or cylinder", other))
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Topology::Flat => write!(f, "flat"),
+            Topology::Cylinder => write!(f, "cylinder")
+        }
+    }
+}
+
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 
 impl Board {
     pub fn new() -> Self {
         Self {
-            data: [[Player::default(); COLS as usize]; ROWS as usize]
+            data: vec![vec![Cell::Empty; COLS as usize]; ROWS as usize],
+            rows: ROWS,
+            cols: COLS,
+            win_length: CONNECTED_COMPONENTS_WIN_THRESHOLD,
+            variant: Variant::Standard,
+            topology: Topology::Flat,
+            captured: vec![],
+            hash: 0,
+            mirrored_hash: 0,
+        }
+    }
+
+    /// Empty board of `rows` by `cols` where `win_length` discs in a line win.
+    pub fn with_size(rows: u8, cols: u8, win_length: u8) -> Result<Self, String> {
+        for (name, size) in [("rows", rows), ("columns", cols)] {
+            if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+                return Err(format!("The number of {} must be between {} and {}, got {}", name, MIN_SIZE, MAX_SIZE, size));
+            }
+        }
+        if !(3..=rows.min(cols)).contains(&win_length) {
+            return Err(format!("The win length must be between 3 and {}, got {}", rows.min(cols), win_length));
+        }
+        Ok(Self { data: vec![vec![Cell::Empty; cols as usize]; rows as usize], rows, cols, win_length, ..Board::new() })
+    }
+
+    /// Standard size for 2 players, wider and taller boards for 3 and 4 players.
+    pub fn for_players(count: usize) -> Self {
+        match count {
+            3 => Board::with_size(7, 9, CONNECTED_COMPONENTS_WIN_THRESHOLD),
+            4 => Board::with_size(8, 10, CONNECTED_COMPONENTS_WIN_THRESHOLD),
+            _ => Ok(Board::new())
+        }.unwrap_or_default()
+    }
+
+    /// Discs in a line needed to win.
+    pub fn win_length(&self) -> u8 {
+        self.win_length
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// Cell at (`row`, `col`) with the column wrapped around on a cylinder, `None` off the board.
+    pub fn cell_at(&self, row: i16, col: i16) -> Option<(u8, u8)> {
+        let col = match self.topology {
+            Topology::Flat => col,
+            Topology::Cylinder => col.rem_euclid(self.cols as i16)
+        };
+        ((0..self.rows as i16).contains(&row) && (0..self.cols as i16).contains(&col)).then_some((row as u8, col as u8))
+    }
+
+    pub fn cell(&self, row: u8, col: u8) -> &Cell {
+        &self.data[row as usize][col as usize]
+    }
+
+    /// Rows of cells, the top row first.
+    pub fn cells(&self) -> &[Vec<Cell>] {
+        &self.data
+    }
+
+    /// Puts a blocker on the empty cell (`row`, `col`).
+    pub fn set_blocked(&mut self, row: u8, col: u8) -> Result<(), String> {
+        if row >= self.rows || col >= self.cols {
+            return Err(format!("Cell {}:{} is not on the {} by {} board", row, col, self.rows, self.cols));
+        }
+        if *self.cell(row, col) != Cell::Empty {
+            return Err(format!("Cell {}:{} is not empty", row, col));
+        }
+        self.put(row, col, Cell::Blocked);
+        Ok(())
+    }
+
+    /// Cells (row, col) holding a blocker, top to bottom and left to right.
+    pub fn blocked_cells(&self) -> Vec<(u8, u8)> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+            .filter(|(row, col)| *self.cell(*row, *col) == Cell::Blocked)
+            .collect()
+    }
+
+    /// Whether this is the flat 6 by 7 connect four board without blockers, the only one opening books are made for.
+    pub fn has_standard_rules(&self) -> bool {
+        (self.rows, self.cols, self.win_length, self.variant, self.topology)
+            == (ROWS, COLS, CONNECTED_COMPONENTS_WIN_THRESHOLD, Variant::Standard, Topology::Flat)
+            && self.blocked_cells().is_empty()
+    }
+
+    /// Incrementally maintained Zobrist hash of the position.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Hash recomputed from scratch, `hash` should always equal it.
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (row, cells) in self.data.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                hash ^= zobrist_key(row as u8, col as u8, cell.id());
+            }
+        }
+        for (id, count) in self.captured.iter().enumerate() {
+            hash ^= captured_key(id as u8, *count);
+        }
+        hash
+    }
+
+    /// Recomputes both hashes, call after changing the cells in bulk.
+    pub fn rehash(&mut self) {
+        self.hash = self.compute_hash();
+        self.mirrored_hash = self.mirror().compute_hash();
+    }
+
+    /// Places `player` at (`row`, `col`), `Player::default()` empties the cell.
+    pub fn set_cell(&mut self, row: u8, col: u8, player: &Player) {
+        self.put(row, col, if *player == Player::default() { Cell::Empty } else { Cell::Disc(player.clone()) });
+    }
+
+    /// Writes `cell` at (`row`, `col`), keeping the hashes in sync.
+    fn put(&mut self, row: u8, col: u8, cell: Cell) {
+        let mirrored_col = self.mirror_col(col);
+        let old = &mut self.data[row as usize][col as usize];
+        self.hash ^= zobrist_key(row, col, old.id()) ^ zobrist_key(row, col, cell.id());
+        self.mirrored_hash ^= zobrist_key(row, mirrored_col, old.id()) ^ zobrist_key(row, mirrored_col, cell.id());
+        *old = cell;
+    }
+
+    /// Hash the left-right mirrored position would have.
+    pub fn mirrored_hash(&self) -> u64 {
+        self.mirrored_hash
+    }
+
+    /// Identical for a position and its mirror image, used to key books and tables.
+    pub fn canonical_key(&self) -> u64 {
+        std::cmp::min(self.hash, self.mirrored_hash)
+    }
+
+    /// Whether the position itself (rather than its mirror) is the one `canonical_key` refers to.
+    pub fn is_canonical(&self) -> bool {
+        self.hash <= self.mirrored_hash
+    }
+
+    /// Column `col` maps to after mirroring; mirroring twice gives back `col`.
+    pub fn mirror_col(&self, col: u8) -> u8 {
+        self.cols - 1 - col
+    }
+
+    /// Translates a move in this position to the equivalent move in the canonical one, and back.
+    pub fn canonical_move(&self, col: u8) -> u8 {
+        if self.is_canonical() { col } else { self.mirror_col(col) }
+    }
+
+    /// `canonical_move` for every kind of move.
+    pub fn canonical(&self, mv: Move) -> Move {
+        mv.map_cols(|col| self.canonical_move(col))
+    }
+
+    pub fn mirror(&self) -> Board {
+        let mut mirrored = self.clone();
+        for row in mirrored.data.iter_mut() {
+            row.reverse();
+        }
+        std::mem::swap(&mut mirrored.hash, &mut mirrored.mirrored_hash);
+        mirrored
+    }
+
+    /// Drops `player` into `col`, returns the row the disc landed on.
+    pub fn drop_piece(&mut self, col: u8, player: &Player) -> Option<u8> {
+        if player.id == BLOCKER_ID {
+            return None; // it would hash like a blocker
         }
+        let row = self.apply_gravity(col)?;
+        self.put(row, col, Cell::Disc(player.clone()));
+        Some(row)
+    }
+
+    /// Row of the topmost disc in `col`.
+    pub fn top_row(&self, col: u8) -> Option<u8> {
+        if col >= self.cols {
+            return None;
+        }
+        (0..self.rows).find(|row| *self.cell(*row, col) != Cell::Empty)
+    }
+
+    /// Removes the topmost disc of `col`, returns the player that owned it. Blockers stay.
+    pub fn undo_move(&mut self, col: u8) -> Option<Player> {
+        let row = self.top_row(col)?;
+        let Cell::Disc(player) = self.cell(row, col).clone() else {
+            return None;
+        };
+        self.put(row, col, Cell::Empty);
+        Some(player)
+    }
+
+    /// Whether the variant lets `player` pop the bottom disc of `col`.
+    pub fn can_pop(&self, col: u8, player: &Player) -> bool {
+        self.variant.allows_pop()
+            && col < self.cols
+            && self.cell(self.rows - 1, col).is_disc_of(player)
+    }
+
+    /// Top row of the discs a pop in `col` moves, the ones above the lowest blocker stay where they are.
+    fn pop_top(&self, col: u8) -> u8 {
+        (0..self.rows)
+            .rev()
+            .find(|row| *self.cell(*row, col) == Cell::Blocked)
+            .map_or(0, |row| row + 1)
+    }
+
+    /// Removes the bottom disc of `col`, the discs above it fall one row.
+    pub fn pop_piece(&mut self, col: u8) -> Option<Player> {
+        if col >= self.cols {
+            return None;
+        }
+        let Cell::Disc(bottom) = self.cell(self.rows - 1, col).clone() else {
+            return None;
+        };
+        let top = self.pop_top(col);
+        for row in (top + 1..self.rows).rev() {
+            self.put(row, col, self.cell(row - 1, col).clone());
+        }
+        self.put(top, col, Cell::Empty);
+        Some(bottom)
+    }
+
+    /// Pushes `player` back in under `col`, undoing `pop_piece`.
+    pub fn unpop_piece(&mut self, col: u8, player: &Player) {
+        for row in self.pop_top(col)..self.rows - 1 {
+            self.put(row, col, self.cell(row + 1, col).clone());
+        }
+        self.put(self.rows - 1, col, Cell::Disc(player.clone()));
+    }
+
+    /// Discs `player` collected in Pop 10.
+    pub fn captured(&self, player: &Player) -> u8 {
+        self.captured.get(player.id as usize).copied().unwrap_or(0)
+    }
+
+    /// Discs collected by all players together.
+    pub fn total_captured(&self) -> u8 {
+        self.captured.iter().sum()
+    }
+
+    /// Sets the discs `player` collected, keeping the hashes in sync.
+    pub fn set_captured(&mut self, player: &Player, count: u8) {
+        let id = player.id as usize;
+        if self.captured.len() <= id {
+            self.captured.resize(id + 1, 0);
+        }
+        let key = captured_key(player.id, self.captured[id]) ^ captured_key(player.id, count);
+        self.hash ^= key;
+        self.mirrored_hash ^= key;
+        self.captured[id] = count;
+    }
+
+    /// Whether a Pop 10 game has moved on from filling the board to popping discs.
+    pub fn is_popping(&self) -> bool {
+        self.variant == Variant::Pop10 && (self.total_captured() > 0 || self.is_full())
+    }
+
+    /// Whether the disc at (`row`, `col`) is part of `win_length` discs of its owner in a line.
+    pub fn in_line(&self, row: u8, col: u8) -> bool {
+        let Cell::Disc(player) = self.cell(row, col) else {
+            return false;
+        };
+        let run = |d_row: i16, d_col: i16| (1..self.win_length as i16)
+            .map_while(|step| self.cell_at(row as i16 + d_row * step, col as i16 + d_col * step))
+            .take_while(|(r, c)| self.cell(*r, *c).is_disc_of(player))
+            .count();
+        [(0, 1), (1, 0), (1, 1), (1, -1)]
+            .iter()
+            .any(|(d_row, d_col)| 1 + run(*d_row, *d_col) + run(-d_row, -d_col) >= self.win_length as usize)
+    }
+
+    /// In the popping phase of Pop 10: `Some(true)` when `player` may pop and keep the bottom disc of `col`,
+    /// `Some(false)` when popping it means dropping it back, `None` when it is not theirs to pop.
+    fn pop10_keeps(&self, col: u8, player: &Player) -> Option<bool> {
+        let bottom = self.rows - 1;
+        (self.is_popping() && col < self.cols && self.cell(bottom, col).is_disc_of(player))
+            .then(|| self.in_line(bottom, col))
+    }
+
+    /// Whether a disc popped from `from` can be dropped back into `to`.
+    fn can_return(&self, from: u8, to: u8) -> bool {
+        to < self.cols && (to == from || !self.is_column_full(to))
+    }
+
+    /// Drops and, when the variant allows them, pops `player` can make.
+    /// Whether `player` has no legal move, which draws the game.
+    pub fn is_stuck(&self, player: &Player) -> bool {
+        (self.is_full() || self.is_popping()) && self.legal_moves(player).is_empty()
+    }
+
+    pub fn legal_moves(&self, player: &Player) -> Vec<Move> {
+        if self.is_popping() {
+            return (0..self.cols)
+                .flat_map(|from| match self.pop10_keeps(from, player) {
+                    Some(true) => vec![Move::Capture(from)],
+                    Some(false) => (0..self.cols).filter(|to| self.can_return(from, *to)).map(|to| Move::Return(from, to)).collect(),
+                    None => vec![]
+                })
+                .collect();
+        }
+        let drops = (0..self.cols).filter(|col| self.is_valid_move(*col)).map(Move::Drop);
+        let pops = (0..self.cols).filter(|col| self.can_pop(*col, player)).map(Move::Pop);
+        drops.chain(pops).collect()
+    }
+
+    pub fn is_legal(&self, mv: Move, player: &Player) -> bool {
+        match mv {
+            Move::Drop(col) => !self.is_popping() && self.is_valid_move(col),
+            Move::Pop(col) => self.can_pop(col, player),
+            Move::Capture(col) => self.pop10_keeps(col, player) == Some(true),
+            Move::Return(from, to) => self.pop10_keeps(from, player) == Some(false) && self.can_return(from, to)
+        }
+    }
+
+    /// Makes `mv` for `player` if it is legal, returns the cell (row, col) the move changed.
+    pub fn play(&mut self, mv: Move, player: &Player) -> Option<(u8, u8)> {
+        match mv {
+            Move::Drop(col) if !self.is_popping() => self.drop_piece(col, player).map(|row| (row, col)),
+            Move::Pop(col) if self.can_pop(col, player) => self.pop_piece(col).map(|_| (self.rows - 1, col)),
+            Move::Capture(col) if self.is_legal(mv, player) => {
+                self.pop_piece(col)?;
+                self.set_captured(player, self.captured(player) + 1);
+                Some((self.rows - 1, col))
+            },
+            Move::Return(from, to) if self.is_legal(mv, player) => {
+                self.pop_piece(from)?;
+                self.drop_piece(to, player).map(|row| (row, to))
+            },
+            _ => None
+        }
+    }
+
+    /// Takes back `mv`, made by `player`.
+    pub fn unplay(&mut self, mv: Move, player: &Player) {
+        match mv {
+            Move::Drop(col) => { self.undo_move(col); },
+            Move::Pop(col) => self.unpop_piece(col, player),
+            Move::Capture(col) => {
+                self.unpop_piece(col, player);
+                self.set_captured(player, self.captured(player).saturating_sub(1));
+            },
+            Move::Return(from, to) => {
+                self.undo_move(to);
+                self.unpop_piece(from, player);
+            }
+        }
+    }
+
+    pub fn disc_count(&self) -> u8 {
+        self.data
+            .iter()
+            .flatten()
+            .filter(|cell| matches!(cell, Cell::Disc(_)))
+            .count() as u8
+    }
+
+    /// Plays a move string such as "3344" (one column digit per ply), players taking turns in order.
+    pub fn play_moves(&mut self, moves: &str, players: &[Player]) -> Result<(), String> {
+        for c in moves.trim().chars() {
+            let col = c.to_digit(10).ok_or(format!("Invalid move '{}' in \"{}\"", c, moves))? as u8;
+            let player = &players[self.disc_count() as usize % players.len()];
+            if self.drop_piece(col, player).is_none() {
+                return Err(format!("Illegal move {} in \"{}\"", col, moves));
+            }
+        }
+        Ok(())
     }
 
     pub fn get_available_cols(&self) -> Option<Vec<u8>> {
         let mut available_cols = vec![];
-        for i in 0..COLS {
+        for i in 0..self.cols {
             if self.is_valid_move(i) {
                 available_cols.push(i);
             }
@@ -29,55 +526,50 @@ impl Board {
         if available_cols.is_empty() {
             return None;
         }
-        println!("AVAILABLE = {:?}", available_cols);
         Some(available_cols)
     }
     
     pub fn is_full(&self) -> bool {
-        // data[0] is top row
         self.data[0]
             .par_iter()
-            .all(|player| *player != Player::default())
+            .all(|cell| *cell != Cell::Empty)
     }
     
+    /// Whether `col` takes no more discs, the holes below a blocker never fill up.
     pub fn is_column_full(&self, col: u8) -> bool {
-        self.data
-            .par_iter()
-            .all(|row| row[col as usize] != Player::default())
+        *self.cell(0, col) != Cell::Empty
     }
     
     pub fn is_valid_move(&self, col: u8) -> bool {
-        col < COLS && !self.is_column_full(col) && !self.is_full()
+        col < self.cols && !self.is_column_full(col) && !self.is_full()
     }
     
-    pub fn apply_gravity(&mut self, col: u8) -> Option<u8> {
+    pub fn apply_gravity(&self, col: u8) -> Option<u8> {
         if !self.is_valid_move(col) {
             return None;
         }
-        for row in (0..ROWS).rev() {
-            if self.data[row as usize][col as usize] == Player::default() {
-                return Some(row); // we reached an empty spot
-            }
-        }
-        None
+        // the disc comes to rest on the first disc or blocker from the top, or on the bottom
+        let support = (0..self.rows)
+            .find(|row| *self.cell(*row, col) != Cell::Empty)
+            .unwrap_or(self.rows);
+        support.checked_sub(1)
     }
 
+    /// Removes every disc, blockers stay.
     pub fn clear(&mut self) {
         self.data
             .par_iter_mut()
             .for_each(|row| row
                 .iter_mut()
-                .for_each(|player| {
-                    player.id = 0;
-                    player.name = "";
-                    player.marker = ' ';
-                    player.player_type = PlayerType::COMPUTER;
-                })
+                .filter(|cell| matches!(cell, Cell::Disc(_)))
+                .for_each(|cell| *cell = Cell::Empty)
             );
+        self.captured.clear();
+        self.rehash();
     }
 
-    pub fn get_rows(&self) -> u8 { ROWS }
-    pub fn get_cols(&self) -> u8 { COLS }
+    pub fn get_rows(&self) -> u8 { self.rows }
+    pub fn get_cols(&self) -> u8 { self.cols }
     
     pub fn generate_full_board() -> Board {
         let mut b = Board::new();
@@ -91,18 +583,19 @@ impl Board {
         p4.marker = '@';
 
         for (i, row) in b.data.iter_mut().enumerate() {
-            for (j, player) in row.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
                 if i % 2 == 0 && j % 2 == 0 {
-                    *player = p1;
+                    *cell = Cell::Disc(p1.clone());
                 } else if i % 2 != 0 && j % 2 == 0 {
-                    *player = p2;
+                    *cell = Cell::Disc(p2.clone());
                 } else if i % 2 == 0 && j % 2 != 0 {
-                    *player = p3;
+                    *cell = Cell::Disc(p3.clone());
                 } else if i % 2 != 0 && j % 2 != 0 {
-                    *player = p4;
+                    *cell = Cell::Disc(p4.clone());
                 }
             }
         }
+        b.rehash();
         println!("Generated full drawn board");
         println!("{}", b);
         b
@@ -112,7 +605,7 @@ impl Board {
     pub fn generate_horizontal_win(player: &Player, nr_connected_components: u8) -> Board {
         let mut b = Board::new();
         for i in 0..nr_connected_components {
-            b.data[ROWS as usize - 1][i as usize] = *player;
+            b.set_cell(ROWS - 1, i, player);
         }
         println!("Generated horizontally won board");
         println!("{}", b);
@@ -123,7 +616,7 @@ impl Board {
     pub fn generate_vertical_win(player: &Player, nr_connected_components: u8) -> Board {
         let mut b = Board::new();
         for i in 0..nr_connected_components {
-            b.data[i as usize][COLS as usize - 1] = *player;
+            b.set_cell(i, COLS - 1, player);
         }
         println!("Generated vertically won board");
         println!("{}", b);
@@ -133,7 +626,7 @@ impl Board {
     pub fn generate_diagonal_south_east_win(player: &Player, nr_connected_components: u8) -> Board {
         let mut b = Board::new();
         for i in 0..nr_connected_components {
-            b.data[i as usize][i as usize] = *player;
+            b.set_cell(i, i, player);
         }
         println!("Generated vertically won board");
         println!("{}", b);
@@ -143,7 +636,7 @@ impl Board {
     pub fn generate_diagonal_north_east_win(player: &Player, nr_connected_components: u8) -> Board {
         let mut b = Board::new();
         for i in 0..nr_connected_components {
-            b.data[ROWS as usize - i as usize - 1][i as usize] = *player;
+            b.set_cell(ROWS - i - 1, i, player);
         }
         println!("Generated vertically won board");
         println!("{}", b);
@@ -155,26 +648,31 @@ impl Board {
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, " ")?;
-        for i in 0..COLS {
+        for i in 0..self.cols {
             write!(f, " {} ", i)?;
         }
         writeln!(f)?;
         write!(f, " ")?;
-        for i in 0..COLS {
+        for _ in 0..self.cols {
             write!(f, " - ")?;
         }
         writeln!(f)?;
 
         for row in self.data.iter() {
             write!(f, "|")?;
-            for player in row.iter() {
-                write!(f, " {} ", player.marker)?;
+            for cell in row.iter() {
+                let marker = match cell {
+                    Cell::Empty => ' ',
+                    Cell::Blocked => '#',
+                    Cell::Disc(player) => player.marker
+                };
+                write!(f, " {} ", marker)?;
             }
             write!(f, "|")?;
             writeln!(f)?;
         }
         write!(f, " ")?;
-        for i in 0..COLS {
+        for _ in 0..self.cols {
             write!(f, " - ")?;
         }
         writeln!(f)?;
@@ -184,8 +682,10 @@ impl fmt::Display for Board {
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::field_reassign_with_default)]
 mod board_tests {
     use super::*;
+    use crate::player::PlayerType;
 
     #[test]
     fn gravity_works() {
@@ -195,7 +695,7 @@ mod board_tests {
         
         let mut p = Player::default();
         p.marker = 'x';
-        b.data[ROWS as usize - 1][0] = p; // hardcode player p in first column
+        b.set_cell(ROWS - 1, 0, &p); // hardcode player p in first column
         println!("{}", b);
         
         assert_eq!(b.apply_gravity(0), Some(b.get_rows() - 2)); // now expect it to be one higher
@@ -215,11 +715,188 @@ mod board_tests {
 
         // fill column 0 with players
         for i in 0..b.get_rows() {
-            b.data[i as usize][0] = p1;
+            b.set_cell(i, 0, &p1);
         }
         
         assert_eq!(b.is_column_full(0), true);
     }
 
+    #[test]
+    fn hash_follows_drops_and_undos() {
+        let mut b = Board::new();
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        let p2 = Player::new(2, "two", PlayerType::COMPUTER, 'o');
+
+        b.drop_piece(3, &p1);
+        let after_one_move = b.hash();
+        assert_ne!(after_one_move, 0);
+
+        b.drop_piece(3, &p2);
+        b.drop_piece(4, &p1);
+        assert_eq!(b.hash(), b.compute_hash());
+
+        assert_eq!(b.undo_move(4), Some(p1.clone()));
+        assert_eq!(b.undo_move(3), Some(p2.clone()));
+        assert_eq!(b.hash(), after_one_move);
+        assert_eq!(b.undo_move(3), Some(p1.clone()));
+        assert_eq!(b.hash(), 0);
+        assert_eq!(b.undo_move(3), None);
+    }
+
+    #[test]
+    fn hash_is_independent_of_move_order() {
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        let p2 = Player::new(2, "two", PlayerType::COMPUTER, 'o');
+
+        let mut a = Board::new();
+        a.drop_piece(0, &p1);
+        a.drop_piece(6, &p2);
+        a.drop_piece(2, &p1);
+
+        let mut b = Board::new();
+        b.drop_piece(2, &p1);
+        b.drop_piece(6, &p2);
+        b.drop_piece(0, &p1);
+
+        assert_eq!(a.hash(), b.hash());
+    }
+
+    #[test]
+    fn mirrored_positions_share_canonical_key() {
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        let p2 = Player::new(2, "two", PlayerType::COMPUTER, 'o');
+
+        let mut b = Board::new();
+        b.drop_piece(0, &p1);
+        b.drop_piece(1, &p2);
+        b.drop_piece(1, &p1);
+
+        let mut mirrored_by_moves = Board::new();
+        mirrored_by_moves.drop_piece(6, &p1);
+        mirrored_by_moves.drop_piece(5, &p2);
+        mirrored_by_moves.drop_piece(5, &p1);
+
+        let mirrored = b.mirror();
+        assert_eq!(mirrored.cells(), mirrored_by_moves.cells());
+        assert_eq!(mirrored.hash(), mirrored_by_moves.hash());
+        assert_eq!(mirrored.mirrored_hash(), b.hash());
+        assert_ne!(b.hash(), mirrored.hash());
+        assert_eq!(b.canonical_key(), mirrored.canonical_key());
+        assert_ne!(b.is_canonical(), mirrored.is_canonical());
+
+        // the same move translates to the same canonical move from either side
+        assert_eq!(b.canonical_move(2), mirrored.canonical_move(b.mirror_col(2)));
+        assert_eq!(b.mirror_col(b.mirror_col(2)), 2);
+    }
+
+    #[test]
+    fn boards_can_be_resized() {
+        let mut b = Board::with_size(8, 10, 5).unwrap();
+        assert_eq!((b.get_rows(), b.get_cols(), b.win_length()), (8, 10, 5));
+        assert!(!b.has_standard_rules() && Board::new().has_standard_rules());
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        assert_eq!(b.drop_piece(9, &p1), Some(7));
+        assert_eq!(b.mirror_col(9), 0);
+        assert!(!b.is_valid_move(10));
+        assert!(b.to_string().starts_with("  0  1  2  3  4  5  6  7  8  9 \n"));
 
+        assert!(Board::with_size(3, 7, 3).is_err());
+        assert!(Board::with_size(6, 11, 4).is_err());
+        assert!(Board::with_size(5, 7, 6).is_err());
+        assert_eq!(Board::for_players(4).get_cols(), 10);
+    }
+
+    #[test]
+    fn popping_shifts_the_column_down() {
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        let p2 = Player::new(2, "two", PlayerType::COMPUTER, 'o');
+        let mut b = Board::new();
+        b.play_moves("3433", &[p1.clone(), p2.clone()]).unwrap();
+        assert!(!b.can_pop(3, &p1));
+        b.set_variant(Variant::PopOut);
+        assert!(b.can_pop(3, &p1) && !b.can_pop(3, &p2) && !b.can_pop(0, &p1));
+        assert_eq!(b.legal_moves(&p2), (0..7).map(Move::Drop).chain([Move::Pop(4)]).collect::<Vec<Move>>());
+
+        let before = b.clone();
+        assert_eq!(b.play(Move::Pop(3), &p1), Some((5, 3)));
+        assert_eq!((b.cell(5, 3), b.cell(4, 3), b.cell(3, 3)), (&Cell::Disc(p1.clone()), &Cell::Disc(p2.clone()), &Cell::Empty));
+        assert_eq!(b.hash(), b.compute_hash());
+        b.unplay(Move::Pop(3), &p1);
+        assert_eq!((b.cells(), b.hash()), (before.cells(), before.hash()));
+    }
+
+    #[test]
+    fn pop10_keeps_discs_in_a_line() {
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        let p2 = Player::new(2, "two", PlayerType::COMPUTER, 'o');
+        let mut b = Board::with_size(4, 4, 3).unwrap();
+        b.set_variant(Variant::Pop10);
+        for (row, cells) in ["2121", "1212", "2211", "1112"].iter().enumerate() {
+            for (col, cell) in cells.chars().enumerate() {
+                b.set_cell(row as u8, col as u8, if cell == '1' { &p1 } else { &p2 });
+            }
+        }
+        assert!(b.is_popping() && !b.is_legal(Move::Drop(0), &p1));
+        assert_eq!(b.legal_moves(&p1), [0, 1, 2].map(Move::Capture));
+        assert_eq!(b.legal_moves(&p2), [Move::Return(3, 3)]);
+
+        let before = b.clone();
+        assert_eq!(b.play(Move::Capture(1), &p1), Some((3, 1)));
+        assert_eq!((b.captured(&p1), b.total_captured()), (1, 1));
+        assert_eq!(b.hash(), b.compute_hash());
+        assert_eq!(b.legal_moves(&p2), [Move::Return(1, 1), Move::Return(3, 1), Move::Return(3, 3)]);
+        assert_eq!(b.play(Move::Return(3, 1), &p2), Some((0, 1)));
+        assert_eq!(b.hash(), b.compute_hash());
+        b.unplay(Move::Return(3, 1), &p2);
+        b.unplay(Move::Capture(1), &p1);
+        assert_eq!((b.cells(), b.hash(), b.captured(&p1)), (before.cells(), before.hash(), 0));
+    }
+
+    #[test]
+    fn blockers_hold_up_discs() {
+        let p1 = Player::new(1, "one", PlayerType::COMPUTER, 'x');
+        let p2 = Player::new(2, "two", PlayerType::COMPUTER, 'o');
+        let mut b = Board::new();
+        for (row, col) in parse_cells("3:2,5:0").unwrap() {
+            b.set_blocked(row, col).unwrap();
+        }
+        assert!(b.set_blocked(3, 2).is_err() && b.set_blocked(6, 0).is_err());
+        assert_eq!(format_cells(&b.blocked_cells()), "3:2,5:0");
+        assert!(!b.has_standard_rules());
+
+        b.play_moves("2220", &[p1.clone(), p2.clone()]).unwrap();
+        assert_eq!((b.cell(2, 2), b.cell(4, 0), b.cell(5, 2)), (&Cell::Disc(p1.clone()), &Cell::Disc(p2.clone()), &Cell::Empty));
+        assert!(b.is_column_full(2) && !b.is_valid_move(2) && b.disc_count() == 4);
+        for _ in 0..3 {
+            b.undo_move(2);
+        }
+        assert_eq!((b.undo_move(2), b.cell(3, 2)), (None, &Cell::Blocked));
+
+        b.set_variant(Variant::PopOut);
+        b.set_cell(5, 2, &p1);
+        b.set_cell(4, 2, &p2);
+        b.drop_piece(2, &p2);
+        let before = b.clone();
+        assert!(b.play(Move::Pop(2), &p1).is_some() && !b.can_pop(0, &p1));
+        assert_eq!((b.cell(5, 2), b.cell(4, 2), b.cell(3, 2), b.cell(2, 2)), (&Cell::Disc(p2.clone()), &Cell::Empty, &Cell::Blocked, &Cell::Disc(p2.clone())));
+        b.unplay(Move::Pop(2), &p1);
+        assert_eq!((b.cells(), b.hash()), (before.cells(), before.hash()));
+
+        b.clear();
+        assert_eq!((b.blocked_cells(), b.disc_count(), b.hash()), (vec![(3, 2), (5, 0)], 0, b.compute_hash()));
+        assert!(parse_cells("3-2").is_err() && parse_cells("-").unwrap().is_empty());
+    }
+
+    #[test]
+    fn blockers_belong_to_no_player() {
+        let impostor = Player { id: BLOCKER_ID, marker: '#', ..Player::default() };
+        let mut b = Board::new();
+        for col in 0..4 {
+            b.set_blocked(5, col).unwrap();
+        }
+        assert_eq!(b.drop_piece(5, &impostor), None);
+        assert!(!b.can_pop(0, &impostor));
+        let strategies = crate::game::Game::setup_win_condition_strategies();
+        assert!(!strategies.iter().any(|strategy| strategy.is_met(&impostor, &b)));
+    }
 }
\ No newline at end of file
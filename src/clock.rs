@@ -0,0 +1,235 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::player::Player;
+use crate::protocol::GoLimits;
+
+/// Time added after the main time, written as `+<seconds>` or `b<periods>x<seconds>`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Overtime {
+    None,
+    Increment(Duration),
+    ByoYomi { periods: u32, period: Duration }
+}
+
+/// Main time per player plus overtime, e.g. `300+5` or `600b5x30`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TimeControl {
+    pub main: Duration,
+    pub overtime: Overtime
+}
+
+/// Chess clock for all players of a game, at most one clock runs at a time.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: Vec<Duration>,
+    periods: Vec<u32>,
+    running: Option<(usize, Instant)>
+}
+
+
+fn parse_seconds(text: &str, control: &str) -> Result<Duration, String> {
+    text.parse::<u64>()
+        .map(Duration::from_secs)
+        .map_err(|e| format!("Invalid time control \"{}\": {}", control, e))
+}
+
+/// `m:ss` above a minute, `s.t` seconds below.
+pub fn format_time(time: Duration) -> String {
+    if time >= Duration::from_secs(60) {
+        format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60)
+    } else {
+        format!("{}.{}", time.as_secs(), time.subsec_millis() / 100)
+    }
+}
+
+
+impl TimeControl {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some((main, increment)) = text.split_once('+') {
+            return Ok(Self { main: parse_seconds(main, text)?, overtime: Overtime::Increment(parse_seconds(increment, text)?) });
+        }
+        if let Some((main, byo_yomi)) = text.split_once('b') {
+            let (periods, period) = byo_yomi.split_once('x').ok_or(format!("Invalid byo-yomi in \"{}\"", text))?;
+            let periods = periods.parse::<u32>().map_err(|e| format!("Invalid time control \"{}\": {}", text, e))?;
+            return Ok(Self { main: parse_seconds(main, text)?, overtime: Overtime::ByoYomi { periods, period: parse_seconds(period, text)? } });
+        }
+        Ok(Self { main: parse_seconds(text, text)?, overtime: Overtime::None })
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.main.as_secs())?;
+        match self.overtime {
+            Overtime::None => Ok(()),
+            Overtime::Increment(increment) => write!(f, "+{}", increment.as_secs()),
+            Overtime::ByoYomi { periods, period } => write!(f, "b{}x{}", periods, period.as_secs())
+        }
+    }
+}
+
+
+impl Clock {
+    pub fn new(control: TimeControl, players: usize) -> Self {
+        let periods = match control.overtime {
+            Overtime::ByoYomi { periods, .. } => periods,
+            _ => 0
+        };
+        Self { control, remaining: vec![control.main; players], periods: vec![periods; players], running: None }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// Starts the clock of `player_index`, stopping any other clock first.
+    pub fn start(&mut self, player_index: usize) -> Result<(), usize> {
+        self.stop()?;
+        self.running = Some((player_index, Instant::now()));
+        Ok(())
+    }
+
+    /// Restarts the running clock from now without charging the time spent so far, e.g. waiting for engines to start.
+    pub fn restart_turn(&mut self) {
+        if let Some((_, started)) = self.running.as_mut() {
+            *started = Instant::now();
+        }
+    }
+
+    /// Stops the running clock and adds overtime, `Err` with the player's index if they ran out of time.
+    pub fn stop(&mut self) -> Result<(), usize> {
+        let Some((index, started)) = self.running.take() else {
+            return Ok(());
+        };
+        self.charge(index, started.elapsed())
+    }
+
+    fn charge(&mut self, index: usize, elapsed: Duration) -> Result<(), usize> {
+        let main = self.remaining[index];
+        self.remaining[index] = main.saturating_sub(elapsed);
+        let overrun = elapsed.saturating_sub(main);
+        match self.control.overtime {
+            Overtime::None | Overtime::Increment(_) if overrun > Duration::ZERO || elapsed == main => Err(index),
+            Overtime::Increment(increment) => {
+                self.remaining[index] += increment;
+                Ok(())
+            },
+            Overtime::None => Ok(()),
+            Overtime::ByoYomi { period, .. } => {
+                let used_periods = (overrun.as_millis() / period.as_millis().max(1)) as u32;
+                if used_periods >= self.periods[index] {
+                    self.periods[index] = 0;
+                    return Err(index);
+                }
+                self.periods[index] -= used_periods;
+                Ok(())
+            }
+        }
+    }
+
+    /// Time `player_index` may still think before their flag falls, including the running move.
+    pub fn time_left(&self, player_index: usize) -> Duration {
+        let reserve = match self.control.overtime {
+            Overtime::ByoYomi { period, .. } => period * self.periods[player_index],
+            _ => Duration::ZERO
+        };
+        let elapsed = match self.running {
+            Some((index, started)) if index == player_index => started.elapsed(),
+            _ => Duration::ZERO
+        };
+        (self.remaining[player_index] + reserve).saturating_sub(elapsed)
+    }
+
+    /// Player whose running clock has no time left.
+    pub fn flagged(&self) -> Option<usize> {
+        self.running
+            .map(|(index, _)| index)
+            .filter(|index| self.time_left(*index) == Duration::ZERO)
+    }
+
+    /// Clock times in the form of a `go` command for the players with clock indices `order`, in that order.
+    /// Overtime is passed as increment.
+    pub fn go_limits(&self, order: &[usize]) -> GoLimits {
+        let overtime = match self.control.overtime {
+            Overtime::None => None,
+            Overtime::Increment(increment) => Some(increment),
+            Overtime::ByoYomi { period, .. } => Some(period)
+        };
+        let mut limits = GoLimits::default();
+        for ((time, increment), index) in limits.times.iter_mut().zip(limits.increments.iter_mut()).zip(order) {
+            *time = Some(self.time_left(*index));
+            *increment = overtime;
+        }
+        limits
+    }
+
+    /// One line with every player's time, the running clock marked with `*`.
+    pub fn describe(&self, players: &[Player]) -> String {
+        players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| {
+                let running = if self.running.is_some_and(|(running, _)| running == index) { "*" } else { " " };
+                let periods = match self.control.overtime {
+                    Overtime::ByoYomi { .. } => format!(" ({} periods)", self.periods[index]),
+                    _ => String::new()
+                };
+                format!("{}{} {}{}", running, player, format_time(self.time_left(index)), periods)
+            })
+            .collect::<Vec<_>>()
+            .join("   ")
+    }
+}
+
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+
+    #[test]
+    fn time_controls_round_trip() {
+        for text in ["300", "300+5", "600b5x30"] {
+            assert_eq!(TimeControl::parse(text).unwrap().to_string(), text);
+        }
+        assert_eq!(
+            TimeControl::parse("60+2").unwrap(),
+            TimeControl { main: Duration::from_secs(60), overtime: Overtime::Increment(Duration::from_secs(2)) }
+        );
+        assert!(TimeControl::parse("5m").is_err());
+        assert!(TimeControl::parse("600b5").is_err());
+    }
+
+    #[test]
+    fn increment_is_added_and_flag_falls() {
+        let mut clock = Clock::new(TimeControl::parse("10+2").unwrap(), 2);
+        assert_eq!(clock.charge(0, Duration::from_secs(4)), Ok(()));
+        assert_eq!(clock.time_left(0), Duration::from_secs(8));
+        assert_eq!(clock.go_limits(&[0, 1]).increments[0], Some(Duration::from_secs(2)));
+        assert_eq!(clock.charge(1, Duration::from_secs(11)), Err(1));
+        assert_eq!(clock.time_left(1), Duration::ZERO);
+    }
+
+    #[test]
+    fn byo_yomi_periods_are_used_up() {
+        let mut clock = Clock::new(TimeControl::parse("10b2x5").unwrap(), 2);
+        assert_eq!(clock.time_left(0), Duration::from_secs(20));
+        assert_eq!(clock.charge(0, Duration::from_secs(14)), Ok(()));
+        assert_eq!(clock.time_left(0), Duration::from_secs(10));
+        assert_eq!(clock.charge(0, Duration::from_secs(6)), Ok(()));
+        assert_eq!(clock.time_left(0), Duration::from_secs(5));
+        assert_eq!(clock.charge(0, Duration::from_secs(5)), Err(0));
+    }
+
+    #[test]
+    fn running_clock_is_shown() {
+        let mut clock = Clock::new(TimeControl::parse("90").unwrap(), 2);
+        let players = crate::game::Game::generate_simulation_players();
+        clock.start(1).unwrap();
+        assert!(clock.describe(&players).starts_with(" x 1:30   *o 1:"));
+        assert_eq!(clock.flagged(), None);
+        assert_eq!(format_time(Duration::from_millis(9_400)), "9.4");
+    }
+}
@@ -0,0 +1,429 @@
+use std::time::{Duration, Instant};
+
+use crate::board::{zobrist_key, Board, Cell, Topology};
+use crate::player::Player;
+use crate::transposition_table::{Bound, TableEntry, TranspositionTable};
+use crate::variant::Move;
+use crate::win_condition_strategy::{decide_winner, StrategyOutcome, WinConditionStrategy};
+
+/// Score of a win; faster wins score higher since the disc count is subtracted.
+pub const WIN_SCORE: i16 = 10_000;
+pub const DEFAULT_SEARCH_DEPTH: u8 = 8;
+const TABLE_SIZE: usize = 1 << 18;
+const NODES_BETWEEN_CLOCK_CHECKS: u64 = 1024;
+/// A collected Pop 10 disc outweighs the lines it was taken from.
+const CAPTURE_SCORE: i16 = 100;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i16,
+    pub depth: u8,
+    pub nodes: u64
+}
+
+/// Iterative deepening negamax with alpha-beta pruning and a transposition table
+/// keyed by `Board::canonical_key` and the side to move, so mirrored positions share entries.
+/// With more than two players the search is paranoid: every opponent plays against the searching player.
+#[derive(Debug)]
+pub struct Engine {
+    table: TranspositionTable,
+    win_condition_strategies: Vec<WinConditionStrategy>,
+    max_depth: u8,
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+    stopped: bool,
+    nodes: u64,
+    root: usize // the searching player
+}
+
+
+pub fn is_decisive(score: i16) -> bool {
+    score.abs() > WIN_SCORE - 100
+}
+
+
+impl Engine {
+    pub fn new(max_depth: u8) -> Self {
+        Self::with_table(max_depth, TranspositionTable::new(TABLE_SIZE))
+    }
+
+    pub fn with_table(max_depth: u8, table: TranspositionTable) -> Self {
+        Self {
+            table,
+            win_condition_strategies: vec![
+                WinConditionStrategy::HorizontalWinStrategy,
+                WinConditionStrategy::VerticalWinStrategy,
+                WinConditionStrategy::DiagonalWinStrategy,
+                WinConditionStrategy::CollectionWinStrategy,
+            ],
+            max_depth,
+            time_limit: None,
+            deadline: None,
+            stopped: false,
+            nodes: 0,
+            root: 0
+        }
+    }
+
+    /// Stops deepening once `time_limit` has passed, keeping the last fully searched depth.
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.time_limit = time_limit;
+    }
+
+    pub fn table(&self) -> &TranspositionTable {
+        &self.table
+    }
+
+    pub fn max_depth(&self) -> u8 {
+        self.max_depth
+    }
+
+    /// Searches the best move for `players[player_index]`, score is from their point of view.
+    pub fn search(&mut self, board: &Board, players: &[Player], player_index: usize) -> SearchResult {
+        self.search_with_info(board, players, player_index, |_| {})
+    }
+
+    /// Like `search`, calling `on_depth` after every completed iteration.
+    pub fn search_with_info<F: FnMut(&SearchResult)>(&mut self,
+                                                      board: &Board,
+                                                      players: &[Player],
+                                                      player_index: usize,
+                                                      mut on_depth: F) -> SearchResult {
+        self.nodes = 0;
+        self.stopped = false;
+        self.root = player_index;
+        self.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let mut board = board.clone();
+        let mut result = SearchResult {
+            best_move: Engine::ordered_moves(&board, &players[player_index], None).first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0
+        };
+
+        for depth in 1..=self.max_depth {
+            let (score, best_move) = self.negamax(&mut board, players, player_index, depth, -WIN_SCORE, WIN_SCORE);
+            if self.stopped {
+                break; // out of time, this iteration is incomplete
+            }
+            result = SearchResult { best_move, score, depth, nodes: self.nodes };
+            on_depth(&result);
+            if is_decisive(score) {
+                break; // forced result, searching deeper changes nothing
+            }
+        }
+        result
+    }
+
+    /// Like `search`, but also scores finished positions instead of searching them.
+    pub fn search_position(&mut self, board: &Board, players: &[Player], player_index: usize) -> SearchResult {
+        let finished = |score| SearchResult { best_move: None, score, depth: 0, nodes: 0 };
+        let win = WIN_SCORE - board.disc_count() as i16;
+        let previous = (player_index + players.len() - 1) % players.len();
+        if let Some(winner) = decide_winner(&self.win_condition_strategies, board, players, previous) {
+            finished(if winner == player_index { win } else { -win })
+        } else if board.is_stuck(&players[player_index]) {
+            finished(0)
+        } else {
+            self.search(board, players, player_index)
+        }
+    }
+
+    /// Index of the winner once `players[mover]` made `mv`, only a pop can decide the game for someone else.
+    fn winner(&self, board: &Board, players: &[Player], mover: usize, mv: Move) -> Option<usize> {
+        match mv {
+            Move::Pop(_) => decide_winner(&self.win_condition_strategies, board, players, mover),
+            _ => self.win_condition_strategies
+                .iter()
+                .find_map(|strategy| strategy.outcome(&players[mover], board))
+                .map(|outcome| outcome.winner(mover, players.len(), |_| true))
+        }
+    }
+
+    fn ordered_moves(board: &Board, player: &Player, first: Option<Move>) -> Vec<Move> {
+        let center = board.get_cols() / 2;
+        let mut moves = board.legal_moves(player);
+        moves.sort_by_key(|mv| (Some(*mv) != first, !matches!(mv, Move::Drop(_) | Move::Capture(_)), mv.col().abs_diff(center)));
+        moves
+    }
+
+    /// Score of the position after `players[player_index]` made `mv`, `None` while the game goes on.
+    fn decided_score(&self, board: &Board, players: &[Player], player_index: usize, mv: Move) -> Option<i16> {
+        if let Some(winner) = self.winner(board, players, player_index, mv) {
+            let win = WIN_SCORE - board.disc_count() as i16;
+            let same_side = (winner == self.root) == (player_index == self.root);
+            return Some(if same_side { win } else { -win });
+        }
+        let next = &players[(player_index + 1) % players.len()];
+        board.is_stuck(next).then_some(0)
+    }
+
+    fn negamax(&mut self,
+               board: &mut Board,
+               players: &[Player],
+               player_index: usize,
+               depth: u8,
+               mut alpha: i16,
+               mut beta: i16) -> (i16, Option<Move>) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(NODES_BETWEEN_CLOCK_CHECKS)
+            && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return (0, None);
+        }
+        if depth == 0 {
+            let score = evaluate(board, &players[self.root]);
+            return (if player_index == self.root { score } else { -score }, None);
+        }
+
+        let alpha_orig = alpha;
+        let player = &players[player_index];
+        // in Pop 10 the same discs come up with either side to move
+        let side_to_move = board.canonical_key() ^ zobrist_key(u8::MAX, u8::MAX, player.id);
+        let key = match players.len() {
+            2 => side_to_move,
+            _ => side_to_move ^ zobrist_key(u8::MAX, u8::MAX - 1, self.root as u8 + 1) // scores depend on the root
+        };
+        let mut table_move = None;
+        if let Some(entry) = self.table.probe(key) {
+            table_move = entry.best_move.map(|mv| board.canonical(mv));
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return (entry.score, table_move),
+                    Bound::Lower => alpha = alpha.max(entry.score),
+                    Bound::Upper => beta = beta.min(entry.score)
+                }
+                if alpha >= beta {
+                    return (entry.score, table_move);
+                }
+            }
+        }
+
+        let next_index = (player_index + 1) % players.len();
+        // opponents of the root share a side, the score only flips between the root and an opponent
+        let flips = (player_index == self.root) != (next_index == self.root);
+        let mut best_score = -WIN_SCORE;
+        let mut best_move = None;
+
+        for mv in Engine::ordered_moves(board, player, table_move) {
+            if board.play(mv, player).is_none() {
+                continue;
+            }
+            let score = if let Some(score) = self.decided_score(board, players, player_index, mv) {
+                score
+            } else if flips {
+                -self.negamax(board, players, next_index, depth - 1, -beta, -alpha).0
+            } else {
+                self.negamax(board, players, next_index, depth - 1, alpha, beta).0
+            };
+            board.unplay(mv, player);
+            if self.stopped {
+                return (0, None);
+            }
+
+            if best_move.is_none() || score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if best_move.is_none() {
+            return (0, None); // no legal moves
+        }
+
+        let bound = if best_score <= alpha_orig {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(key, TableEntry {
+            score: best_score,
+            depth,
+            bound,
+            best_move: best_move.map(|mv| board.canonical(mv))
+        });
+        (best_score, best_move)
+    }
+}
+
+
+/// Static evaluation from `player`'s point of view: every window of
+/// `Board::win_length` cells that only one side occupies
+/// counts for that side, weighted by how filled it is (against it in misère), plus a bonus for the center column
+/// and, in Pop 10, the discs collected.
+pub fn evaluate(board: &Board, player: &Player) -> i16 {
+    let rows = board.get_rows() as i16;
+    let cols = board.get_cols() as i16;
+    let length = board.win_length() as i16;
+    let directions = [(0, 1), (1, 0), (1, 1), (-1, 1)];
+    let mut score = 0;
+
+    for row in 0..rows {
+        for col in 0..cols {
+            for (d_row, d_col) in directions {
+                // windows run over the edge of a cylinder, off a flat board or through a blocker they are skipped
+                let window: Vec<&Cell> = (0..length)
+                    .map_while(|offset| board.cell_at(row + d_row * offset, col + d_col * offset))
+                    .map(|(r, c)| board.cell(r, c))
+                    .collect();
+                if window.len() < length as usize || window.iter().any(|cell| **cell == Cell::Blocked) {
+                    continue;
+                }
+                let mut mine = 0;
+                let mut theirs = 0;
+                for cell in window {
+                    match cell {
+                        Cell::Disc(disc) if disc == player => mine += 1,
+                        Cell::Disc(_) => theirs += 1,
+                        _ => {}
+                    }
+                }
+                score += match (mine, theirs) {
+                    (m, 0) if m > 0 => m * m,
+                    (0, t) if t > 0 => -(t * t),
+                    _ => 0
+                };
+            }
+        }
+    }
+
+    if board.variant().line_outcome() == Some(StrategyOutcome::Loss) {
+        score = -score;
+    }
+
+    // a cylinder has no centre column
+    let center = (cols / 2) as u8;
+    for row in (0..rows as u8).filter(|_| board.topology() == Topology::Flat) {
+        match board.cell(row, center) {
+            Cell::Disc(disc) if disc == player => score += 3,
+            Cell::Disc(_) => score -= 3,
+            _ => {}
+        }
+    }
+    let captured = board.captured(player) as i16;
+    score + CAPTURE_SCORE * (2 * captured - board.total_captured() as i16)
+}
+
+
+#[cfg(test)]
+mod engine_tests {
+    use super::*;
+    use crate::player::PlayerType;
+
+    fn players() -> Vec<Player> {
+        vec![
+            Player::new(1, "one", PlayerType::COMPUTER, 'x'),
+            Player::new(2, "two", PlayerType::COMPUTER, 'o'),
+        ]
+    }
+
+    #[test]
+    fn takes_immediate_win() {
+        let players = players();
+        let mut b = Board::new();
+        b.play_moves("001122", &players).unwrap();
+        let result = Engine::new(4).search(&b, &players, 0);
+        assert_eq!(result.best_move, Some(Move::Drop(3)));
+        assert!(is_decisive(result.score) && result.score > 0);
+    }
+
+    #[test]
+    fn blocks_opponent_win() {
+        let players = players();
+        let mut b = Board::new();
+        b.play_moves("06060", &players).unwrap();
+        // player two must block column 0 before anything else
+        let result = Engine::new(4).search(&b, &players, 1);
+        assert_eq!(result.best_move, Some(Move::Drop(0)));
+    }
+
+    #[test]
+    fn table_entries_belong_to_the_side_to_move() {
+        let players = players();
+        let mut b = Board::new();
+        b.play_moves("06060", &players).unwrap();
+        let mut engine = Engine::new(4);
+        assert!(engine.search(&b, &players, 0).score > 0);
+        // the same discs with player two to move are no win for them
+        assert_eq!(engine.search(&b, &players, 1).score, Engine::new(4).search(&b, &players, 1).score);
+    }
+
+    #[test]
+    fn time_limit_stops_deepening() {
+        let players = players();
+        let mut engine = Engine::new(40);
+        engine.set_time_limit(Some(Duration::from_millis(50)));
+        let started = Instant::now();
+        let mut depths = vec![];
+        let result = engine.search_with_info(&Board::new(), &players, 0, |info| depths.push(info.depth));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(result.depth < 40 && result.best_move.is_some());
+        assert_eq!(depths.last(), Some(&result.depth));
+    }
+
+    #[test]
+    fn mirrored_positions_get_mirrored_moves() {
+        let players = players();
+        let mut b = Board::new();
+        b.play_moves("001122", &players).unwrap();
+        let result = Engine::new(4).search(&b.mirror(), &players, 0);
+        assert_eq!(result.best_move, Some(Move::Drop(b.mirror_col(3))));
+    }
+
+    #[test]
+    fn opponents_gang_up_on_the_leader() {
+        let mut players = players();
+        players.push(Player::new(3, "three", PlayerType::COMPUTER, 'v'));
+        let mut b = Board::for_players(3);
+        // one threatens column 3, two has to block since three counts as an opponent too
+        for (row, col, player) in [(6, 0, 0), (6, 1, 0), (6, 2, 0), (6, 8, 1), (5, 8, 1), (6, 7, 2), (5, 7, 2)] {
+            b.set_cell(row, col, &players[player]);
+        }
+        let result = Engine::new(3).search(&b, &players, 1);
+        assert_eq!(result.best_move, Some(Move::Drop(3)));
+        assert!(Engine::new(3).search(&b, &players, 0).score > 0);
+    }
+
+    #[test]
+    fn misere_avoids_completing_lines() {
+        let players = players();
+        let mut b = Board::new();
+        b.set_variant(crate::variant::Variant::Misere);
+        b.play_moves("010101", &players).unwrap();
+        let result = Engine::new(4).search(&b, &players, 0);
+        assert_ne!(result.best_move, Some(Move::Drop(0)));
+        b.play_moves("0", &players).unwrap();
+        assert_eq!(Engine::new(4).search_position(&b, &players, 1).score, WIN_SCORE - 7);
+    }
+
+    #[test]
+    fn cylinder_wins_over_the_edge() {
+        let players = players();
+        let mut b = Board::new();
+        b.play_moves("536303", &players).unwrap();
+        assert_eq!(Engine::new(4).search(&b, &players, 0).best_move, Some(Move::Drop(3)));
+        b.set_topology(Topology::Cylinder);
+        let result = Engine::new(4).search(&b, &players, 0);
+        assert!(matches!(result.best_move, Some(Move::Drop(1 | 4))));
+        assert!(is_decisive(result.score) && result.score > 0);
+    }
+
+    #[test]
+    fn finished_positions_score_for_the_winner() {
+        let players = players();
+        let mut b = Board::new();
+        b.play_moves("0101010", &players).unwrap();
+        let result = Engine::new(4).search_position(&b, &players, 1);
+        assert_eq!((result.score, result.best_move), (-(WIN_SCORE - 7), None));
+        assert_eq!(Engine::new(4).search_position(&Board::generate_full_board(), &players, 0).score, 0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::sync::mpsc::Sender;
+
+use crate::game::Game;
+use crate::player::Player;
+
+/// Something that happened in a `Game`, passed to every observer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GameEvent {
+    Started,
+    MovePlayed { player: Player, row: u8, col: u8 },
+    Popped { player: Player, col: u8 },
+    Captured { player: Player, col: u8 }, // Pop 10: a popped disc is kept
+    InvalidMove { player: Player, col: u8 },
+    ActionRejected { player: Player, reason: String }, // a resignation, draw offer or abort that is not allowed
+    Flagged { player: Player },
+    Resigned { player: Player },
+    DrawOffered { player: Player },
+    Aborted { player: Player },
+    Won { player: Player, line: Vec<(u8, u8)> },
+    Draw,
+    Reset
+}
+
+/// Reacts to game events, `game` is already in the state after the event.
+pub trait GameObserver: Send + Sync {
+    fn notify(&mut self, game: &Game, event: &GameEvent);
+}
+
+impl<F: FnMut(&Game, &GameEvent) + Send + Sync> GameObserver for F {
+    fn notify(&mut self, game: &Game, event: &GameEvent) {
+        self(game, event)
+    }
+}
+
+/// Forwards events to a channel, a dropped receiver is ignored.
+impl GameObserver for Sender<GameEvent> {
+    fn notify(&mut self, _game: &Game, event: &GameEvent) {
+        let _ = self.send(event.clone());
+    }
+}
+
+
+/// The command line output of a game.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConsoleObserver;
+
+impl ConsoleObserver {
+    /// Text printed for `event`, `None` for events without output.
+    pub fn describe(game: &Game, event: &GameEvent) -> Option<String> {
+        match event {
+            GameEvent::Started => Some(format!("Welcome to connect four!\n{}", game.render_board())),
+            GameEvent::MovePlayed { row, col, .. } => {
+                Some(format!("Player moved (row, column): ({}, {})\n{}", row, col, game.render_board()))
+            },
+            GameEvent::Popped { col, .. } => {
+                Some(format!("Player popped column {}\n{}", col, game.render_board()))
+            },
+            GameEvent::Captured { col, .. } => {
+                Some(format!("Player captured the disc in column {}\n{}", col, game.render_board()))
+            },
+            GameEvent::InvalidMove { player, .. } => Some(format!("Invalid move by: {}", player)),
+            GameEvent::ActionRejected { player, reason } => Some(format!("{}: {}", player.name, reason)),
+            GameEvent::Flagged { player } => Some(format!("Player {} ran out of time!", player.name)),
+            GameEvent::Resigned { player } => Some(format!("Player {} resigned!", player.name)),
+            GameEvent::DrawOffered { player } => Some(format!("Player {} offers a draw", player.name)),
+            GameEvent::Aborted { player } => Some(format!("Player {} aborted the game", player.name)),
+            GameEvent::Won { player, .. } => Some(format!("Player {} won!", player.name)),
+            GameEvent::Draw => Some(String::from("It's a draw!")),
+            GameEvent::Reset => Some(String::from("Resetting game..."))
+        }
+    }
+}
+
+impl GameObserver for ConsoleObserver {
+    fn notify(&mut self, game: &Game, event: &GameEvent) {
+        if let Some(text) = ConsoleObserver::describe(game, event) {
+            println!("{}", text);
+        }
+    }
+}
+
+
+/// Registered observers, in registration order.
+#[derive(Default)]
+pub struct Observers(pub Vec<Box<dyn GameObserver>>);
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
+
+
+#[cfg(test)]
+mod events_tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn closures_observe_moves_and_results() {
+        let mut game = Game::create_simulated_game();
+        let seen = Arc::new(Mutex::new(vec![]));
+        let log = Arc::clone(&seen);
+        game.add_observer(move |game: &Game, event: &GameEvent| {
+            log.lock().unwrap().push((event.clone(), game.moves().len()));
+        });
+        for col in [0, 1, 0, 1, 0, 1] {
+            game.play_move(col).unwrap();
+        }
+        assert!(game.play_move(9).is_err());
+        game.play_move(0).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 9);
+        assert_eq!(seen[0].1, 1);
+        assert_eq!(seen[6].0, GameEvent::InvalidMove { player: game.players()[0].clone(), col: 9 });
+        assert!(matches!(&seen[8].0, GameEvent::Won { player, .. } if *player == game.players()[0]));
+    }
+
+    #[test]
+    fn console_output_describes_events() {
+        let mut game = Game::create_simulated_game();
+        game.set_renderer(crate::render::Renderer::plain());
+        let player = game.current_player();
+        game.play_move(3).unwrap();
+
+        let moved = ConsoleObserver::describe(&game, &GameEvent::MovePlayed { player, row: 5, col: 3 }).unwrap();
+        assert!(moved.starts_with("Player moved (row, column): (5, 3)\n"));
+        assert!(moved.ends_with(&game.render_board()));
+        assert_eq!(ConsoleObserver::describe(&game, &GameEvent::Draw).unwrap(), "It's a draw!");
+    }
+}
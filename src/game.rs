@@ -1,15 +1,90 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver};
 use rayon::prelude::*;
-use crate::player::{Player, PlayerType};
-use crate::board::Board;
-use crate::utils::{cli_confirms, get_cli_input};
-use crate::win_condition_strategy::WinConditionStrategy;
+use crate::player::{Player, PlayerAction, PlayerType};
+use crate::protocol::{ExternalEngine, GoLimits};
+use crate::board::{zobrist_key, Board};
+use crate::clock::{Clock, TimeControl};
+use crate::engine::DEFAULT_SEARCH_DEPTH;
+use crate::events::{GameEvent, GameObserver, Observers};
+use crate::render::Renderer;
+use crate::utils::cli_confirms;
+use crate::variant::{Move, Variant, POP10_TARGET};
+use crate::win_condition_strategy::{StrategyOutcome, WinConditionStrategy};
+
+pub const MAX_PLAYERS: usize = 4;
+const PLAYER_NAMES: [&str; MAX_PLAYERS] = ["henk-one", "henk-two", "henk-three", "henk-four"];
+const PLAYER_MARKERS: [char; MAX_PLAYERS] = ['x', 'o', 'v', '@'];
+const REPETITIONS_FOR_DRAW: usize = 3;
 
 #[derive(Debug, Eq, PartialEq)]
 enum TurnOutcome {
     ContinueGame,
     ExitGame,
     NewGame,
-    InvalidMove
+    InvalidMove,
+    DrawOffered,
+    DrawAgreed,
+    Resigned,
+    Aborted,
+    GameOver
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GameStatus {
+    InProgress,
+    Won(Player),
+    Draw,
+    Aborted
+}
+
+/// How a finished game ended.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Termination {
+    Normal,
+    TimeForfeit,
+    Resignation,
+    Agreement,
+    Abandoned,
+    Repetition
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Termination::Normal => write!(f, "normal"),
+            Termination::TimeForfeit => write!(f, "time forfeit"),
+            Termination::Resignation => write!(f, "resignation"),
+            Termination::Agreement => write!(f, "agreement"),
+            Termination::Abandoned => write!(f, "abandoned"),
+            Termination::Repetition => write!(f, "repetition")
+        }
+    }
+}
+
+/// A player who resigned or ran out of time while the others played on.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Elimination {
+    pub player_index: usize,
+    pub ply: usize, // moves played before the player went out
+    pub termination: Termination
+}
+
+/// Stage of the game, only Pop 10 moves on from filling the board to popping discs.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GamePhase {
+    Filling,
+    Popping
+}
+
+impl fmt::Display for GamePhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GamePhase::Filling => write!(f, "filling"),
+            GamePhase::Popping => write!(f, "popping")
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -17,6 +92,19 @@ pub struct Game {
     board: Board,
     players: Vec<Player>,
     win_condition_strategies: Vec<WinConditionStrategy>,
+    renderer: Renderer,
+    last_move: Option<(u8, u8)>,
+    moves: Vec<Move>,
+    movers: Vec<usize>, // index of the player behind every move
+    positions: Vec<u64>, // position key after every move, for repetitions
+    first_player_index: usize,
+    eliminated: Vec<bool>,
+    eliminations: Vec<Elimination>,
+    observers: Observers,
+    clock: Option<Clock>,
+    ended: Option<(GameStatus, Termination)>,
+    draw_offers: Vec<usize>,
+    engines: Vec<Option<Arc<Mutex<ExternalEngine>>>> // process of every `ENGINE` player, kept for all games
 }
 
 
@@ -26,12 +114,386 @@ impl Game {
                win_condition_strategies: Vec<WinConditionStrategy>) -> Self {
         Self {
             board,
+            eliminated: vec![false; players.len()],
+            eliminations: vec![],
             players,
-            win_condition_strategies
+            win_condition_strategies,
+            renderer: Renderer::detect(),
+            last_move: None,
+            moves: vec![],
+            movers: vec![],
+            positions: vec![],
+            first_player_index: 0,
+            observers: Observers::default(),
+            clock: None,
+            ended: None,
+            draw_offers: vec![],
+            engines: vec![]
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    /// Moves played so far, in order.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Index of the player behind every move.
+    pub fn movers(&self) -> &[usize] {
+        &self.movers
+    }
+
+    pub fn last_move(&self) -> Option<(u8, u8)> {
+        self.last_move
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.players[self.current_player_index()].clone()
+    }
+
+    /// The first player still in the game after the last mover.
+    pub fn current_player_index(&self) -> usize {
+        self.active_from(self.movers.last().map_or(self.first_player_index, |last| last + 1))
+    }
+
+    /// The first player still in the game from seat `start` on.
+    fn active_from(&self, start: usize) -> usize {
+        let count = self.players.len();
+        (start..start + count)
+            .map(|index| index % count)
+            .find(|index| !self.eliminated[*index])
+            .unwrap_or(start % count)
+    }
+
+    /// Players that resigned or ran out of time are out, the game goes on without them.
+    pub fn is_eliminated(&self, player_index: usize) -> bool {
+        self.eliminated[player_index]
+    }
+
+    /// Players who went out so far, in order.
+    pub fn eliminations(&self) -> &[Elimination] {
+        &self.eliminations
+    }
+
+    /// Players still in the game, in seating order.
+    pub fn active_players(&self) -> Vec<Player> {
+        self.players
+            .iter()
+            .zip(&self.eliminated)
+            .filter(|(_, eliminated)| !**eliminated)
+            .map(|(player, _)| player.clone())
+            .collect()
+    }
+
+    pub fn first_player_index(&self) -> usize {
+        self.first_player_index
+    }
+
+    /// Lets `players[index]` move first, only before the first move.
+    pub fn set_first_player(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.players.len() {
+            return Err(format!("There is no player {}", index + 1));
+        }
+        if !self.moves.is_empty() {
+            return Err(String::from("The game has already started"));
+        }
+        self.first_player_index = index;
+        self.set_time_control(self.time_control());
+        Ok(())
+    }
+
+    /// Players starting with the one who moved first.
+    pub fn players_in_move_order(&self) -> Vec<Player> {
+        let mut players = self.players.clone();
+        players.rotate_left(self.first_player_index);
+        players
+    }
+
+    pub fn phase(&self) -> GamePhase {
+        if self.board.is_popping() { GamePhase::Popping } else { GamePhase::Filling }
+    }
+
+    /// When a pop completes lines for several players the one who popped decides the game,
+    /// a player losing by a line hands the win to the next player still in the game
+    /// and a player without legal moves draws the game.
+    pub fn status(&self) -> GameStatus {
+        let decided = self.movers
+            .last()
+            .copied()
+            .into_iter()
+            .chain(0..self.players.len())
+            .filter(|index| !self.eliminated[*index]) // discs left by players who are out decide nothing
+            .find_map(|index| self.outcome(&self.players[index]).map(|outcome| (index, outcome)));
+        if let Some((status, _)) = &self.ended {
+            status.clone()
+        } else if let Some((index, outcome)) = decided {
+            GameStatus::Won(self.players[outcome.winner(index, self.players.len(), |seat| !self.eliminated[seat])].clone())
+        } else if self.board.is_stuck(&self.current_player()) {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// `None` while the game is in progress.
+    pub fn termination(&self) -> Option<Termination> {
+        match (&self.ended, self.status()) {
+            (Some((_, termination)), _) => Some(*termination),
+            (None, GameStatus::InProgress) => None,
+            (None, _) => Some(Termination::Normal)
+        }
+    }
+
+    /// Drops a disc for the current player.
+    pub fn play_move(&mut self, col: u8) -> Result<GameStatus, String> {
+        self.make_move(Move::Drop(col))
+    }
+
+    /// Makes a drop or pop for the current player, this is the single place rules are applied.
+    pub fn make_move(&mut self, mv: Move) -> Result<GameStatus, String> {
+        if self.status() != GameStatus::InProgress {
+            return Err(String::from("The game is already over"));
+        }
+        let player = self.current_player();
+        let player_index = self.current_player_index();
+        if !self.board.is_legal(mv, &player) {
+            self.emit(GameEvent::InvalidMove { player, col: mv.col() });
+            return Err(match mv {
+                Move::Drop(col) if self.board.is_popping() => format!("Cannot drop into column {}, the board is being popped", col),
+                Move::Drop(col) => format!("Invalid column {}", col),
+                Move::Pop(col) => format!("Cannot pop column {}, only your own bottom discs can be popped", col),
+                Move::Capture(col) => format!("Cannot capture column {}, only your own bottom discs in a line can be kept", col),
+                Move::Return(from, to) => format!("Cannot pop column {} into column {}", from, to)
+            });
         }
+        if let Some(Err(flagged)) = self.clock.as_mut().map(Clock::stop) {
+            self.flag(flagged);
+            return Err(format!("Player {} ran out of time", player.name));
+        }
+        let (row, col) = self.board
+            .play(mv, &player)
+            .ok_or(format!("Invalid move {}", mv))?;
+        // moving declines a draw offered by other players
+        if !self.draw_offers.contains(&player_index) {
+            self.draw_offers.clear();
+        }
+        self.moves.push(mv);
+        self.movers.push(player_index);
+        self.last_move = Some((row, col));
+        self.emit(match mv {
+            Move::Drop(_) => GameEvent::MovePlayed { player, row, col },
+            Move::Pop(col) | Move::Return(col, _) => GameEvent::Popped { player, col },
+            Move::Capture(_) => GameEvent::Captured { player, col }
+        });
+
+        let key = self.position_key();
+        self.positions.push(key);
+        if self.status() == GameStatus::InProgress
+            && self.positions.iter().filter(|position| **position == key).count() >= REPETITIONS_FOR_DRAW {
+            self.end(GameStatus::Draw, Termination::Repetition);
+        }
+        let status = self.status();
+        match &status {
+            GameStatus::Won(winner) => {
+                let line = self.winning_cells();
+                self.emit(GameEvent::Won { player: winner.clone(), line });
+            },
+            GameStatus::Draw => self.emit(GameEvent::Draw),
+            GameStatus::InProgress => self.start_clock(),
+            GameStatus::Aborted => {}
+        }
+        Ok(status)
+    }
+
+    /// Plays with a chess clock from now on, or without one for `None`.
+    pub fn set_time_control(&mut self, control: Option<TimeControl>) {
+        self.clock = control.map(|control| Clock::new(control, self.players.len()));
+        self.start_clock();
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.clock.as_ref().map(Clock::control)
+    }
+
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    fn start_clock(&mut self) {
+        let index = self.current_player_index();
+        if let Some(Err(flagged)) = self.clock.as_mut().map(|clock| clock.start(index)) {
+            self.flag(flagged);
+        }
+    }
+
+    /// Ends the game if the running clock has no time left.
+    pub fn check_clock(&mut self) -> GameStatus {
+        if self.status() == GameStatus::InProgress
+            && let Some(flagged) = self.clock.as_ref().and_then(Clock::flagged) {
+            self.flag(flagged);
+        }
+        self.status()
+    }
+
+    /// `players[player_index]` loses on time.
+    pub fn lose_on_time(&mut self, player_index: usize) {
+        if self.check_in_progress(player_index).is_ok() {
+            self.flag(player_index);
+        }
+    }
+
+    /// Loss on time for `players[player_index]`.
+    fn flag(&mut self, player_index: usize) {
+        let player = self.players[player_index].clone();
+        self.eliminate(player_index, Termination::TimeForfeit, GameEvent::Flagged { player });
+    }
+
+    /// Takes `players[player_index]` out of the game, the last player left wins.
+    fn eliminate(&mut self, player_index: usize, termination: Termination, event: GameEvent) {
+        let was_to_move = self.current_player_index() == player_index;
+        self.eliminated[player_index] = true;
+        self.eliminations.push(Elimination { player_index, ply: self.moves.len(), termination });
+        self.draw_offers.retain(|index| *index != player_index);
+        let winner = match &self.active_players()[..] {
+            [winner] => Some(winner.clone()),
+            _ => None
+        };
+        if let Some(winner) = &winner {
+            self.end(GameStatus::Won(winner.clone()), termination);
+        }
+        self.emit(event);
+        if winner.is_none() && self.everyone_agreed() {
+            self.end(GameStatus::Draw, Termination::Agreement);
+            self.emit(GameEvent::Draw);
+            return;
+        }
+        match winner {
+            Some(winner) => self.emit(GameEvent::Won { player: winner, line: vec![] }),
+            None if was_to_move => {
+                if let Some(clock) = self.clock.as_mut() {
+                    let _ = clock.stop(); // their time no longer matters
+                }
+                self.start_clock();
+            },
+            None => {}
+        }
+    }
+
+    /// Ends the game before the board decides it.
+    fn end(&mut self, status: GameStatus, termination: Termination) {
+        if let Some(clock) = self.clock.as_mut() {
+            let _ = clock.stop();
+        }
+        self.draw_offers.clear();
+        self.ended = Some((status, termination));
+    }
+
+    fn check_in_progress(&self, player_index: usize) -> Result<Player, String> {
+        if self.status() != GameStatus::InProgress {
+            return Err(String::from("The game is already over"));
+        }
+        let player = self.players.get(player_index).cloned().ok_or(format!("There is no player {}", player_index + 1))?;
+        if self.eliminated[player_index] {
+            return Err(format!("Player {} is out of the game", player.name));
+        }
+        Ok(player)
+    }
+
+    /// `players[player_index]` gives up, the last player left wins.
+    pub fn resign(&mut self, player_index: usize) -> Result<GameStatus, String> {
+        let player = self.check_in_progress(player_index)?;
+        self.eliminate(player_index, Termination::Resignation, GameEvent::Resigned { player });
+        Ok(self.status())
+    }
+
+    /// Offers a draw, the game is drawn once every player still in it agreed.
+    pub fn offer_draw(&mut self, player_index: usize) -> Result<GameStatus, String> {
+        let player = self.check_in_progress(player_index)?;
+        if self.draw_offers.contains(&player_index) {
+            return Err(String::from("You already offered a draw"));
+        }
+        self.draw_offers.push(player_index);
+        if self.everyone_agreed() {
+            self.end(GameStatus::Draw, Termination::Agreement);
+            self.emit(GameEvent::Draw);
+        } else {
+            self.emit(GameEvent::DrawOffered { player });
+        }
+        Ok(self.status())
+    }
+
+    fn everyone_agreed(&self) -> bool {
+        !self.draw_offers.is_empty()
+            && (0..self.players.len()).all(|index| self.eliminated[index] || self.draw_offers.contains(&index))
+    }
+
+    /// Player who offered the open draw, it stands until a player who did not agree moves.
+    pub fn draw_offer(&self) -> Option<Player> {
+        self.draw_offers.first().map(|index| self.players[*index].clone())
+    }
+
+    /// Ends the game without a result.
+    pub fn abort(&mut self, player_index: usize) -> Result<GameStatus, String> {
+        let player = self.check_in_progress(player_index)?;
+        self.end(GameStatus::Aborted, Termination::Abandoned);
+        self.emit(GameEvent::Aborted { player });
+        Ok(self.status())
+    }
+
+    /// Position and player to move, the same key three times draws the game.
+    fn position_key(&self) -> u64 {
+        self.board.hash() ^ zobrist_key(u8::MAX, u8::MAX, self.current_player().id)
+    }
+
+    /// Takes back the last move, returns it.
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        let mover = self.movers.pop()?;
+        self.positions.pop();
+        if self.termination() == Some(Termination::Repetition) {
+            self.ended = None;
+        }
+        self.board.unplay(mv, &self.players[mover]);
+        self.last_move = self.moves
+            .last()
+            .and_then(|mv| match mv {
+                Move::Drop(col) | Move::Return(_, col) => self.board.top_row(*col).map(|row| (row, *col)),
+                Move::Pop(col) | Move::Capture(col) => Some((self.board.get_rows() - 1, *col))
+            });
+        Some(mv)
+    }
+
+    pub fn add_observer<O: GameObserver + 'static>(&mut self, observer: O) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    /// Receiver for every event from now on.
+    pub fn subscribe(&mut self) -> Receiver<GameEvent> {
+        let (sender, receiver) = channel();
+        self.add_observer(sender);
+        receiver
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in observers.0.iter_mut() {
+            observer.notify(self, &event);
+        }
+        self.observers = observers;
+    }
+
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
     }
     
-    fn create_simulated_game() -> Self {
+    pub fn create_simulated_game() -> Self {
         Game::new(
             Board::new(),
             Game::generate_simulation_players(),
@@ -39,10 +501,31 @@ impl Game {
         )
     }
 
+    /// A human and `count - 1` AI players, seated in that order.
+    pub fn generate_players_for(count: usize) -> Result<Vec<Player>, String> {
+        Game::numbered_players(count, PlayerType::HUMAN, PlayerType::AI(DEFAULT_SEARCH_DEPTH))
+    }
+
+    pub fn generate_simulation_players_for(count: usize) -> Result<Vec<Player>, String> {
+        Game::numbered_players(count, PlayerType::COMPUTER, PlayerType::COMPUTER)
+    }
+
+    fn numbered_players(count: usize, first: PlayerType, others: PlayerType) -> Result<Vec<Player>, String> {
+        if !(2..=MAX_PLAYERS).contains(&count) {
+            return Err(format!("A game needs 2 to {} players, got {}", MAX_PLAYERS, count));
+        }
+        Ok((0..count)
+            .map(|index| {
+                let player_type = if index == 0 { first.clone() } else { others.clone() };
+                Player::new(index as u8 + 1, PLAYER_NAMES[index], player_type, PLAYER_MARKERS[index])
+            })
+            .collect())
+    }
+
     pub fn generate_players() -> Vec<Player> {
         vec![
             Player::new(1, "henk-one", PlayerType::HUMAN, 'x'),
-            Player::new(2, "henk-two", PlayerType::COMPUTER, 'o'),
+            Player::new(2, "henk-two", PlayerType::AI(DEFAULT_SEARCH_DEPTH), 'o'),
         ]
     }
 
@@ -58,108 +541,224 @@ impl Game {
             WinConditionStrategy::HorizontalWinStrategy,
             WinConditionStrategy::VerticalWinStrategy,
             WinConditionStrategy::DiagonalWinStrategy,
+            WinConditionStrategy::CollectionWinStrategy,
         ]
     }
     
 
     pub fn run(&mut self) ->Result<(), String> {
-        println!("Welcome to connect four!");
-        println!("{}", &self.board);
-
-        let mut turn_outcome = TurnOutcome::ContinueGame;
-        let mut player_index = 0;
-
-        while turn_outcome != TurnOutcome::ExitGame {
-            let player = &self.players[player_index].clone();
-            loop {
-                turn_outcome = self.process_turn(player)?;
-                if turn_outcome != TurnOutcome::InvalidMove {
-                    break;
-                }
-            }
-            
-            match turn_outcome {
-                TurnOutcome::ExitGame => break,
-                TurnOutcome::ContinueGame => {
-                    player_index += 1;
-                    player_index = player_index % self.players.len();
-                    println!("{}", &self.board);
-                    continue;
-                },
-                TurnOutcome::InvalidMove => {
-                    println!("Invalid move by: {}", player);
-                    continue;
-                },
-                TurnOutcome::NewGame => {
-                    self.reset_game();
-                    player_index = 0; // start with player 1 again
-                }
+        let mut turn_outcome = TurnOutcome::NewGame;
+
+        while turn_outcome == TurnOutcome::NewGame {
+            self.play_game()?;
+            turn_outcome = Game::ask_for_new_game();
+            if turn_outcome == TurnOutcome::NewGame {
+                self.reset_game();
             }
-            println!("{}", &self.board);
         }
         println!("Game ended!");
         Ok(())
     }
 
 
-    fn process_turn(&mut self, player: &Player) -> Result<TurnOutcome, String> {
-        let chosen_col_move = player.get_move(&self.board)?;
-        if let Some(corresponding_row_move) = self.board.apply_gravity(chosen_col_move) {
-            self.board.data[corresponding_row_move as usize][chosen_col_move as usize] = player.clone();
-            println!("Player moved (row, column): ({}, {})", corresponding_row_move, chosen_col_move);
-            println!("{}", &self.board);
+    /// Plays turns until the current game is over.
+    pub fn play_game(&mut self) -> Result<GameStatus, String> {
+        self.emit(GameEvent::Started);
+        self.start_engines()?;
+        while matches!(self.process_turn()?, TurnOutcome::ContinueGame | TurnOutcome::InvalidMove | TurnOutcome::DrawOffered) {}
+        Ok(self.status())
+    }
 
-            let mut game_over = false;
-            if self.has_won(player) {
-                println!("Player {} won!", player.name);
-                game_over = true;
-            }
-            
-            if self.board.is_full() && !self.has_won(player) {
-                println!("It's a draw!");
-                game_over = true;
+
+    /// Starts the process of every `ENGINE` player that has none yet and tells all of them a new game begins,
+    /// the time this takes is not charged to the clock.
+    pub fn start_engines(&mut self) -> Result<(), String> {
+        self.engines.resize_with(self.players.len(), || None);
+        for (player, engine) in self.players.iter().zip(self.engines.iter_mut()) {
+            if let PlayerType::ENGINE(command) = &player.player_type
+                && engine.is_none() {
+                *engine = Some(Arc::new(Mutex::new(ExternalEngine::spawn(command)?)));
             }
-            
-            if game_over {
-                print!("\nDo you wish to start a new game? y/n: ");
-                if cli_confirms() {
-                    return Ok(TurnOutcome::NewGame);
-                } else {
-                    return Ok(TurnOutcome::ExitGame);
+        }
+        for engine in self.engines.iter().flatten() {
+            engine.lock().map_err(|e| e.to_string())?.new_game()?;
+        }
+        if let Some(clock) = self.clock.as_mut() {
+            clock.restart_turn();
+        }
+        Ok(())
+    }
+
+    /// Engine process of `player_index`, started by `start_engines`.
+    pub fn engine(&self, player_index: usize) -> Option<Arc<Mutex<ExternalEngine>>> {
+        self.engines.get(player_index).cloned().flatten()
+    }
+
+
+    /// Clock times of the current player's turn in the form of a `go` command, none without a clock.
+    pub fn go_limits(&self) -> GoLimits {
+        // eliminated players are skipped, the AI only searches the players still in the game
+        let order: Vec<usize> = (0..self.players.len()).filter(|index| !self.eliminated[*index]).collect();
+        self.clock.as_ref().map(|clock| clock.go_limits(&order)).unwrap_or_default()
+    }
+
+
+    fn process_turn(&mut self) -> Result<TurnOutcome, String> {
+        let player = self.current_player();
+        let player_index = self.current_player_index();
+        let active = self.active_players();
+        let limits = self.go_limits();
+        let draw_offered = !self.draw_offers.is_empty() && !self.draw_offers.contains(&player_index);
+        let engine = self.engine(player_index);
+        let mut engine = match &engine {
+            Some(engine) => Some(engine.lock().map_err(|e| e.to_string())?),
+            None => None
+        };
+        let action = player.get_move(&self.board, &active, &limits, draw_offered, engine.as_deref_mut());
+        drop(engine);
+        if self.check_clock() != GameStatus::InProgress {
+            return Ok(TurnOutcome::GameOver);
+        }
+        if self.current_player_index() != player_index {
+            return Ok(TurnOutcome::ContinueGame); // out of time, the others play on
+        }
+        let mv = match action? {
+            PlayerAction::Move(col) => Move::Drop(col),
+            PlayerAction::Pop(col) => Move::Pop(col),
+            PlayerAction::Capture(col) => Move::Capture(col),
+            PlayerAction::Return(from, to) => Move::Return(from, to),
+            PlayerAction::Resign => {
+                self.resign(player_index)?;
+                return Ok(if self.status() == GameStatus::InProgress { TurnOutcome::ContinueGame } else { TurnOutcome::Resigned });
+            },
+            PlayerAction::OfferDraw => return match self.offer_draw(player_index) {
+                Ok(GameStatus::InProgress) => Ok(TurnOutcome::DrawOffered),
+                Ok(_) => Ok(TurnOutcome::DrawAgreed),
+                Err(reason) => {
+                    self.emit(GameEvent::ActionRejected { player, reason });
+                    self.reject(player_index)
                 }
+            },
+            PlayerAction::Abort => {
+                self.abort(player_index)?;
+                return Ok(TurnOutcome::Aborted);
             }
+        };
+        if self.make_move(mv).is_err()
+            && self.status() == GameStatus::InProgress
+            && self.current_player_index() == player_index {
+            return self.reject(player_index);
+        }
+        if self.status() == GameStatus::InProgress {
             Ok(TurnOutcome::ContinueGame)
         } else {
-            Ok(TurnOutcome::InvalidMove)
+            Ok(TurnOutcome::GameOver)
+        }
+    }
+
+    /// A human is asked again after an invalid action, a computer or engine player would only repeat it and forfeits.
+    fn reject(&mut self, player_index: usize) -> Result<TurnOutcome, String> {
+        if self.players[player_index].player_type == PlayerType::HUMAN {
+            return Ok(TurnOutcome::InvalidMove);
+        }
+        self.resign(player_index)?;
+        Ok(if self.status() == GameStatus::InProgress { TurnOutcome::ContinueGame } else { TurnOutcome::Resigned })
+    }
+
+
+    fn ask_for_new_game() -> TurnOutcome {
+        print!("\nDo you wish to start a new game? y/n: ");
+        if cli_confirms() {
+            TurnOutcome::NewGame
+        } else {
+            TurnOutcome::ExitGame
         }
     }
 
 
-    fn reset_game(&mut self) {
-        println!("Resetting game...");
+    pub fn reset_game(&mut self) {
+        self.restart();
+    }
+
+
+    /// Clears the board and move history.
+    pub fn restart(&mut self) {
         self.board.clear();
+        self.last_move = None;
+        self.moves.clear();
+        self.movers.clear();
+        self.positions.clear();
+        self.eliminated.iter_mut().for_each(|eliminated| *eliminated = false);
+        self.eliminations.clear();
+        self.ended = None;
+        self.draw_offers.clear();
+        self.set_time_control(self.time_control());
+        self.emit(GameEvent::Reset);
+    }
+
+
+    /// Board with the last move and any winning line highlighted, the discs collected below it in Pop 10.
+    pub fn render_board(&self) -> String {
+        let mut board = self.renderer.render(&self.board, self.last_move, &self.winning_cells());
+        if self.board.variant() == Variant::Pop10 {
+            board.push_str(&self.describe_captures());
+        }
+        match &self.clock {
+            Some(clock) => format!("{}\n{}", clock.describe(&self.players), board),
+            None => board
+        }
+    }
+
+
+    /// Phase and discs collected per player, e.g. `popping phase, captured: x 3  o 1 (10 wins)`.
+    fn describe_captures(&self) -> String {
+        let captures: Vec<String> = self.players
+            .iter()
+            .map(|player| format!("{} {}", player, self.board.captured(player)))
+            .collect();
+        format!("{} phase, captured: {} ({} wins)\n", self.phase(), captures.join("  "), POP10_TARGET)
+    }
+
+
+    /// Cells of the winning line, empty while nobody has won.
+    pub fn winning_cells(&self) -> Vec<(u8, u8)> {
+        self.players
+            .iter()
+            .find_map(|player| self.winning_line(player))
+            .unwrap_or_default()
+    }
+
+
+    fn winning_line(&self, player: &Player) -> Option<Vec<(u8, u8)>> {
+        self.win_condition_strategies
+            .iter()
+            .find_map(|strategy| strategy.winning_line(player, &self.board))
     }
 
     
-    fn has_won(&self, player: &Player) -> bool {
+    /// What the win conditions make of `player`'s position, `None` while nothing decides it.
+    fn outcome(&self, player: &Player) -> Option<StrategyOutcome> {
         self.win_condition_strategies
-            .par_iter()
-            .any(|strategy| strategy.has_won(player, &self.board))
+            .iter()
+            .find_map(|strategy| strategy.outcome(player, &self.board))
     }
     
     
-    fn has_winner(&self) -> bool {
+    pub fn has_winner(&self) -> bool {
         self.players
             .par_iter()
-            .any(|player| self.has_won(player))
+            .any(|player| self.outcome(player).is_some())
     }
     
 }
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::useless_vec)]
 mod game_tests {
     use super::*;
+    use crate::board::Cell;
+    use crate::variant::Variant;
 
     #[test]
     fn full_board_has_no_winner() {
@@ -188,10 +787,284 @@ mod game_tests {
     #[test]
     fn legal_move_continues_game() {
         let mut game = Game::create_simulated_game();
-        let player = &game.players[0].clone();
-        let turn_outcome = &game.process_turn(player);
+        let turn_outcome = &game.process_turn();
         assert_eq!(*turn_outcome, Ok(TurnOutcome::ContinueGame));
     }
-    
+
+    #[test]
+    fn moves_alternate_and_undo() {
+        let mut game = Game::create_simulated_game();
+        for col in [0, 1, 0, 1, 0, 1] {
+            assert_eq!(game.play_move(col), Ok(GameStatus::InProgress));
+        }
+        assert_eq!(game.current_player(), game.players[0]);
+        assert_eq!(game.play_move(0), Ok(GameStatus::Won(game.players[0].clone())));
+        assert!(game.play_move(2).is_err());
+
+        assert_eq!(game.undo_move(), Some(Move::Drop(0)));
+        assert_eq!(game.last_move(), Some((3, 1)));
+        assert_eq!(game.status(), GameStatus::InProgress);
+        assert_eq!(game.current_player(), game.players[0]);
+        assert!(game.play_move(7).is_err());
+        assert_eq!(game.moves(), [0, 1, 0, 1, 0, 1].map(Move::Drop));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn engines_sending_illegal_moves_forfeit() {
+        let script = "while read line; do case $line in \
+            cfp) echo cfpok;; \
+            newgame) echo readyok;; \
+            go*) echo 'bestmove 9';; \
+            quit) exit 0;; esac; done";
+        let path = std::env::temp_dir().join(format!("illegal_engine_{}.sh", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+
+        let command: Arc<str> = Arc::from(format!("sh {}", path.display()));
+        let players = vec![
+            Player::new(1, "one", PlayerType::ENGINE(command), 'x'),
+            Player::new(2, "two", PlayerType::COMPUTER, 'o')
+        ];
+        let mut game = Game::new(Board::new(), players, Game::setup_win_condition_strategies());
+        let events = game.subscribe();
+        assert_eq!(game.play_game(), Ok(GameStatus::Won(game.players[1].clone())));
+        assert_eq!(game.termination(), Some(Termination::Resignation));
+        assert!(events.try_iter().any(|event| matches!(event, GameEvent::InvalidMove { col: 9, .. })));
+        drop(game);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn engine_processes_last_for_all_games() {
+        // an engine that logs its starts and new games and plays the columns in turn
+        let log = std::env::temp_dir().join(format!("engine_log_{}.txt", std::process::id()));
+        let script = format!("echo start >> {0}; n=0; while read line; do case $line in \
+            cfp) echo cfpok;; \
+            newgame) echo newgame >> {0}; n=0; echo readyok;; \
+            go*) echo \"bestmove $n\"; n=$((n + 1));; \
+            quit) exit 0;; esac; done", log.display());
+        let path = std::env::temp_dir().join(format!("counting_engine_{}.sh", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+
+        let command: Arc<str> = Arc::from(format!("sh {}", path.display()));
+        let players = vec![
+            Player::new(1, "one", PlayerType::ENGINE(command.clone()), 'x'),
+            Player::new(2, "two", PlayerType::ENGINE(command), 'o')
+        ];
+        let mut game = Game::new(Board::new(), players, Game::setup_win_condition_strategies());
+        for _ in 0..2 {
+            assert_eq!(game.play_game(), Ok(GameStatus::Won(game.players[0].clone())));
+            game.restart();
+        }
+        drop(game);
+
+        let log_lines = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(log_lines.lines().filter(|line| *line == "start").count(), 2);
+        assert_eq!(log_lines.lines().filter(|line| *line == "newgame").count(), 4);
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(log).unwrap();
+    }
+
+    #[test]
+    fn subscribers_receive_events() {
+        let mut game = Game::create_simulated_game();
+        let events = game.subscribe();
+        let (one, two) = (game.players[0].clone(), game.players[1].clone());
+        for col in [0, 1, 0, 1, 0, 1, 0] {
+            game.play_move(col).unwrap();
+        }
+        game.restart();
+
+        let received: Vec<GameEvent> = events.try_iter().collect();
+        assert_eq!(received.len(), 9);
+        assert_eq!(received[0], GameEvent::MovePlayed { player: one.clone(), row: 5, col: 0 });
+        assert_eq!(received[1], GameEvent::MovePlayed { player: two, row: 5, col: 1 });
+        assert_eq!(received[7], GameEvent::Won { player: one, line: vec![(2, 0), (3, 0), (4, 0), (5, 0)] });
+        assert_eq!(received[8], GameEvent::Reset);
+
+        drop(events);
+        assert_eq!(game.play_move(3), Ok(GameStatus::InProgress));
+    }
+
+    #[test]
+    fn second_player_can_start() {
+        let mut game = Game::create_simulated_game();
+        assert!(game.set_first_player(2).is_err());
+        game.set_first_player(1).unwrap();
+        assert_eq!(game.current_player(), game.players[1]);
+        assert_eq!(game.players_in_move_order(), vec![game.players[1].clone(), game.players[0].clone()]);
+        game.play_move(3).unwrap();
+        assert_eq!(game.board().cell(5, 3), &Cell::Disc(game.players[1].clone()));
+        assert!(game.set_first_player(0).is_err());
+        assert_eq!(game.play_game().map(|status| status != GameStatus::InProgress), Ok(true));
+    }
+
+    #[test]
+    fn flag_fall_loses_the_game() {
+        let mut game = Game::create_simulated_game();
+        game.set_time_control(Some(TimeControl::parse("0+5").unwrap()));
+        let events = game.subscribe();
+        assert!(game.play_move(3).is_err());
+        assert_eq!(game.status(), GameStatus::Won(game.players[1].clone()));
+        assert_eq!(game.termination(), Some(Termination::TimeForfeit));
+        assert_eq!(events.try_iter().next(), Some(GameEvent::Flagged { player: game.players[0].clone() }));
+
+        game.restart();
+        game.set_time_control(Some(TimeControl::parse("60").unwrap()));
+        assert_eq!(game.play_move(3), Ok(GameStatus::InProgress));
+        assert_eq!(game.check_clock(), GameStatus::InProgress);
+        let header = game.render_board().lines().next().unwrap().to_string();
+        assert!(header.starts_with(" x ") && header.contains("   *o "));
+    }
+
+    #[test]
+    fn resign_draw_and_abort_end_the_game() {
+        let mut game = Game::create_simulated_game();
+        game.offer_draw(0).unwrap();
+        assert!(game.offer_draw(0).is_err());
+        game.play_move(3).unwrap();
+        assert_eq!(game.draw_offer(), Some(game.players[0].clone()));
+        game.play_move(3).unwrap();
+        assert_eq!(game.draw_offer(), None);
+        game.offer_draw(0).unwrap();
+        assert_eq!(game.offer_draw(1), Ok(GameStatus::Draw));
+        assert_eq!(game.termination(), Some(Termination::Agreement));
+        assert!(game.play_move(4).is_err());
+
+        game.restart();
+        let events = game.subscribe();
+        assert_eq!(game.resign(0), Ok(GameStatus::Won(game.players[1].clone())));
+        assert_eq!(game.termination(), Some(Termination::Resignation));
+        assert_eq!(events.try_iter().next(), Some(GameEvent::Resigned { player: game.players[0].clone() }));
+        assert!(game.abort(1).is_err());
+
+        game.restart();
+        assert_eq!(game.abort(1), Ok(GameStatus::Aborted));
+        assert_eq!(game.termination(), Some(Termination::Abandoned));
+    }
+
+    #[test]
+    fn popout_pops_and_repetitions() {
+        let mut game = Game::create_simulated_game();
+        assert!(game.make_move(Move::Pop(0)).is_err());
+        game.board.set_variant(Variant::PopOut);
+        game.play_move(0).unwrap();
+        assert!(game.make_move(Move::Pop(0)).is_err());
+        game.play_move(1).unwrap();
+        assert_eq!(game.make_move(Move::Pop(0)), Ok(GameStatus::InProgress));
+        assert_eq!(game.last_move(), Some((5, 0)));
+        assert_eq!(game.undo_move(), Some(Move::Pop(0)));
+        assert_eq!(game.board().cell(5, 0), &Cell::Disc(game.players[0].clone()));
+
+        let cycle = [Move::Pop(0), Move::Pop(1), Move::Drop(0), Move::Drop(1)];
+        for mv in cycle.iter().cycle().take(6) {
+            assert_eq!(game.make_move(*mv), Ok(GameStatus::InProgress));
+        }
+        // the position after the first move is back for the third time
+        assert_eq!(game.make_move(Move::Drop(0)), Ok(GameStatus::Draw));
+        assert_eq!(game.termination(), Some(Termination::Repetition));
+        game.undo_move();
+        assert_eq!(game.status(), GameStatus::InProgress);
+    }
+
+    #[test]
+    fn popping_a_line_for_both_players_wins_for_the_popper() {
+        let mut game = Game::create_simulated_game();
+        game.board.set_variant(Variant::PopOut);
+        let (x, o) = (game.players[0].clone(), game.players[1].clone());
+        for (row, col, player) in [(3, 0, &x), (4, 0, &o), (4, 1, &x), (4, 2, &x), (4, 3, &x), (5, 0, &x), (5, 1, &o), (5, 2, &o), (5, 3, &o)] {
+            game.board.set_cell(row, col, player);
+        }
+        assert_eq!(game.make_move(Move::Pop(0)), Ok(GameStatus::Won(x)));
+    }
+
+    #[test]
+    fn misere_lines_lose() {
+        let mut game = Game::create_simulated_game();
+        game.board.set_variant(Variant::Misere);
+        for col in [0, 1, 0, 1, 0, 1] {
+            game.play_move(col).unwrap();
+        }
+        let (x, o) = (game.players[0].clone(), game.players[1].clone());
+        assert_eq!(game.play_move(0), Ok(GameStatus::Won(o)));
+        assert_eq!(game.winning_cells(), vec![(2, 0), (3, 0), (4, 0), (5, 0)]);
+        assert_eq!(game.termination(), Some(Termination::Normal));
+
+        let mut game = Game::new(Board::for_players(3), Game::generate_simulation_players_for(3).unwrap(), Game::setup_win_condition_strategies());
+        game.board.set_variant(Variant::Misere);
+        game.resign(2).unwrap();
+        for col in [0, 1, 0, 1, 0, 1, 2] {
+            game.play_move(col).unwrap();
+        }
+        // the player after the loser wins, skipping players who are out
+        assert_eq!(game.play_move(1), Ok(GameStatus::Won(x)));
+    }
+
+    #[test]
+    fn pop10_fills_the_board_then_collects() {
+        let mut board = Board::with_size(4, 4, 3).unwrap();
+        board.set_variant(Variant::Pop10);
+        let mut game = Game::new(board, Game::generate_simulation_players(), Game::setup_win_condition_strategies());
+        game.set_renderer(Renderer::plain());
+        let (x, o) = (game.players[0].clone(), game.players[1].clone());
+        // every column fills up with a single colour, lines do not end the game
+        for col in [0, 1, 2, 3].repeat(4) {
+            assert_eq!(game.phase(), GamePhase::Filling);
+            assert_eq!(game.play_move(col), Ok(GameStatus::InProgress));
+        }
+        assert_eq!(game.phase(), GamePhase::Popping);
+        assert!(game.play_move(0).is_err());
+        assert!(game.make_move(Move::Capture(1)).is_err());
+        assert_eq!(game.make_move(Move::Capture(0)), Ok(GameStatus::InProgress));
+        assert_eq!(game.make_move(Move::Capture(1)), Ok(GameStatus::InProgress));
+        assert!(game.render_board().ends_with("popping phase, captured: x 1  o 1 (10 wins)\n"));
+
+        game.board.set_captured(&x, POP10_TARGET - 1);
+        assert_eq!(game.make_move(Move::Capture(0)), Ok(GameStatus::Won(x.clone())));
+        assert!(game.winning_cells().is_empty());
+        assert_eq!(game.undo_move(), Some(Move::Capture(0)));
+        assert_eq!((game.status(), game.board().captured(&x), game.board().captured(&o)), (GameStatus::InProgress, POP10_TARGET - 1, 1));
+    }
+
+    #[test]
+    fn eliminated_players_are_skipped() {
+        assert!(Game::generate_players_for(5).is_err());
+        let players = Game::generate_simulation_players_for(3).unwrap();
+        let mut game = Game::new(Board::for_players(3), players.clone(), Game::setup_win_condition_strategies());
+        game.play_move(4).unwrap();
+        assert_eq!(game.current_player(), players[1]);
+        assert_eq!(game.resign(1), Ok(GameStatus::InProgress));
+        assert_eq!(game.current_player(), players[2]);
+        assert!(game.resign(1).is_err());
+        game.play_move(4).unwrap();
+        assert_eq!(game.current_player(), players[0]);
+        assert_eq!(game.active_players(), vec![players[0].clone(), players[2].clone()]);
+
+        game.offer_draw(0).unwrap();
+        assert_eq!(game.offer_draw(2), Ok(GameStatus::Draw));
+        game.restart();
+        game.offer_draw(0).unwrap();
+        assert_eq!(game.resign(2), Ok(GameStatus::InProgress));
+        assert_eq!(game.resign(0), Ok(GameStatus::Won(players[1].clone())));
+        assert_eq!(game.termination(), Some(Termination::Resignation));
+    }
+
+    #[test]
+    fn lines_of_eliminated_players_decide_nothing() {
+        let players = Game::generate_simulation_players_for(3).unwrap();
+        let mut board = Board::for_players(3);
+        board.set_variant(Variant::PopOut);
+        board.set_cell(6, 0, &players[0]);
+        board.set_cell(5, 0, &players[1]);
+        for col in 1..4 {
+            board.set_cell(6, col, &players[1]);
+        }
+        let mut game = Game::new(board, players.clone(), Game::setup_win_condition_strategies());
+        game.resign(1).unwrap();
+        // the pop lines up four discs of the player who resigned
+        assert_eq!(game.make_move(Move::Pop(0)), Ok(GameStatus::InProgress));
+        assert_eq!(game.current_player(), players[2]);
+    }
 }
 
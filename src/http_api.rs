@@ -0,0 +1,389 @@
+//! JSON over HTTP access to games, enabled with the `http` feature.
+//!
+//! - `POST /games`: create a game
+//! - `GET /games[?status=finished|in_progress]`: list games
+//! - `GET /games/<id>`: board state
+//! - `POST /games/<id>/moves` with `{"column": <col>}`: play a move for the player to move
+//! - `POST /games/<id>/ai-move[?depth=<n>]`: let the engine play for the player to move, searching 5 seconds at most
+//! - `GET /games/<id>/analysis[?depth=<n>]`: engine score of every legal move
+//!
+//! Errors are returned as `{"error": "<message>"}` with a 4xx status.
+
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::board::Board;
+use crate::engine::{Engine, DEFAULT_SEARCH_DEPTH, WIN_SCORE};
+use crate::game::{Game, GameStatus};
+use crate::player::{Player, PlayerType};
+use crate::protocol::board_to_rows;
+use crate::variant::Move;
+
+const MAX_DEPTH: u8 = 12;
+/// Longest time a request may spend searching, it is split among the moves of an analysis.
+const SEARCH_TIME: Duration = Duration::from_secs(5);
+
+type ApiResult = Result<(u16, Value), (u16, String)>;
+
+/// Copy of a game's position, searched without holding the lock on the games.
+struct Position {
+    id: u64,
+    board: Board,
+    players: Vec<Player>,
+    index: usize,
+    moves: Vec<Move>
+}
+
+/// Games by id, shared by all request handlers.
+#[derive(Debug, Default)]
+pub struct Api {
+    games: Mutex<BTreeMap<u64, Game>>
+}
+
+
+fn status_name(status: &GameStatus) -> &'static str {
+    match status {
+        GameStatus::InProgress => "in_progress",
+        GameStatus::Won(_) => "won",
+        GameStatus::Draw => "draw",
+        GameStatus::Aborted => "aborted"
+    }
+}
+
+fn game_json(id: u64, game: &Game) -> Value {
+    let status = game.status();
+    json!({
+        "id": id,
+        "moves": game.moves().iter().map(|col| col.to_string()).collect::<String>(),
+        "board": board_to_rows(game.board()).split('/').collect::<Vec<_>>(),
+        "rows": game.board().get_rows(),
+        "cols": game.board().get_cols(),
+        "next_player": if status == GameStatus::InProgress { json!(game.current_player().id) } else { Value::Null },
+        "status": status_name(&status),
+        "winner": match &status {
+            GameStatus::Won(winner) => json!(winner.id),
+            _ => Value::Null
+        },
+        "winning_line": game.winning_cells()
+    })
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn depth_param(query: &str) -> Result<u8, (u16, String)> {
+    match query_param(query, "depth") {
+        None => Ok(DEFAULT_SEARCH_DEPTH),
+        Some(depth) => match depth.parse::<u8>() {
+            Ok(depth) if (1..=MAX_DEPTH).contains(&depth) => Ok(depth),
+            _ => Err((400, format!("depth must be between 1 and {}", MAX_DEPTH)))
+        }
+    }
+}
+
+fn player_index(game: &Game) -> usize {
+    let current = game.current_player();
+    game.players().iter().position(|player| *player == current).unwrap_or(0)
+}
+
+
+impl Api {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes one request, returns the status code and JSON body.
+    pub fn handle(&self, method: &str, url: &str, body: &str) -> (u16, Value) {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let result = match (method, segments.as_slice()) {
+            ("POST", ["games"]) => self.create_game(),
+            ("GET", ["games"]) => self.list_games(query),
+            ("GET", ["games", id]) => self.with_game(id, |id, game| Ok((200, game_json(id, game)))),
+            ("POST", ["games", id, "moves"]) => self.play_move(id, body),
+            ("POST", ["games", id, "ai-move"]) => self.ai_move(id, query),
+            ("GET", ["games", id, "analysis"]) => self.analysis(id, query),
+            (_, ["games"]) | (_, ["games", _]) | (_, ["games", _, "moves" | "ai-move" | "analysis"]) => {
+                Err((405, format!("Method {} not allowed on {}", method, path)))
+            },
+            _ => Err((404, format!("No route for {}", path)))
+        };
+        result.unwrap_or_else(|(status, error)| (status, json!({ "error": error })))
+    }
+
+    fn with_game<T, F: FnOnce(u64, &mut Game) -> Result<T, (u16, String)>>(&self, id: &str, f: F) -> Result<T, (u16, String)> {
+        let id = id.parse::<u64>().map_err(|_| (400, format!("Invalid game id \"{}\"", id)))?;
+        let mut games = self.games.lock().unwrap();
+        let game = games.get_mut(&id).ok_or((404, format!("Game {} not found", id)))?;
+        f(id, game)
+    }
+
+    fn create_game(&self) -> ApiResult {
+        let players = vec![
+            Player::new(1, "player-one", PlayerType::HUMAN, 'x'),
+            Player::new(2, "player-two", PlayerType::HUMAN, 'o'),
+        ];
+        let game = Game::new(Board::new(), players, Game::setup_win_condition_strategies());
+        let mut games = self.games.lock().unwrap();
+        let id = games.keys().next_back().map_or(1, |id| id + 1);
+        let json = game_json(id, &game);
+        games.insert(id, game);
+        Ok((201, json))
+    }
+
+    fn list_games(&self, query: &str) -> ApiResult {
+        let filter = query_param(query, "status");
+        if let Some(filter) = filter.filter(|filter| !["finished", "in_progress"].contains(filter)) {
+            return Err((400, format!("Unknown status filter \"{}\"", filter)));
+        }
+        let games = self.games.lock().unwrap();
+        let listed: Vec<Value> = games
+            .iter()
+            .filter(|(_, game)| match filter {
+                Some("finished") => game.status() != GameStatus::InProgress,
+                Some("in_progress") => game.status() == GameStatus::InProgress,
+                _ => true
+            })
+            .map(|(id, game)| game_json(*id, game))
+            .collect();
+        Ok((200, json!(listed)))
+    }
+
+    fn play_move(&self, id: &str, body: &str) -> ApiResult {
+        let body: Value = serde_json::from_str(body).map_err(|e| (400, format!("Invalid JSON: {}", e)))?;
+        let column = body
+            .get("column")
+            .and_then(Value::as_u64)
+            .ok_or((400, String::from("Body must be {\"column\": <number>}")))?;
+        self.with_game(id, |id, game| {
+            if game.status() != GameStatus::InProgress {
+                return Err((409, String::from("The game is already over")));
+            }
+            let column = u8::try_from(column)
+                .ok()
+                .filter(|col| game.board().is_valid_move(*col))
+                .ok_or((422, format!("Invalid column {}", column)))?;
+            game.play_move(column).map_err(|e| (422, e))?;
+            Ok((200, game_json(id, game)))
+        })
+    }
+
+    fn position(&self, id: &str) -> Result<Position, (u16, String)> {
+        self.with_game(id, |id, game| {
+            if game.status() != GameStatus::InProgress {
+                return Err((409, String::from("The game is already over")));
+            }
+            Ok(Position {
+                id,
+                board: game.board().clone(),
+                players: game.players().to_vec(),
+                index: player_index(game),
+                moves: game.moves().to_vec()
+            })
+        })
+    }
+
+    fn ai_move(&self, id: &str, query: &str) -> ApiResult {
+        let depth = depth_param(query)?;
+        let position = self.position(id)?;
+        let mut engine = Engine::new(depth);
+        engine.set_time_limit(Some(SEARCH_TIME));
+        let result = engine.search(&position.board, &position.players, position.index);
+        let best_move = result.best_move.ok_or((409, String::from("No legal moves")))?;
+        self.with_game(id, |id, game| {
+            if game.moves() != position.moves {
+                return Err((409, String::from("The game changed during the search")));
+            }
+            game.make_move(best_move).map_err(|e| (422, e))?;
+            let column = best_move.col(); // games created over HTTP use the standard rules
+            let mut json = game_json(id, game);
+            json["ai_move"] = json!({ "column": column, "score": result.score, "depth": result.depth });
+            Ok((200, json))
+        })
+    }
+
+    fn analysis(&self, id: &str, query: &str) -> ApiResult {
+        let depth = depth_param(query)?;
+        let Position { id, board: position, players, index, .. } = self.position(id)?;
+        let player = &players[index];
+        let strategies = Game::setup_win_condition_strategies();
+        let time_limit = SEARCH_TIME / (position.get_cols() as u32 + 1);
+        let mut move_scores = vec![];
+        for col in 0..position.get_cols() {
+            let mut board = position.clone();
+            if board.drop_piece(col, player).is_none() {
+                continue;
+            }
+            let score = if strategies.iter().any(|strategy| strategy.has_won(player, &board)) {
+                WIN_SCORE - board.disc_count() as i16
+            } else if board.is_full() {
+                0
+            } else {
+                let mut engine = Engine::new(depth.saturating_sub(1).max(1));
+                engine.set_time_limit(Some(time_limit));
+                -engine.search(&board, &players, (index + 1) % players.len()).score
+            };
+            move_scores.push(json!({ "column": col, "score": score }));
+        }
+        let mut engine = Engine::new(depth);
+        engine.set_time_limit(Some(time_limit));
+        let best = engine.search(&position, &players, index);
+        Ok((200, json!({
+            "id": id,
+            "player": player.id,
+            "depth": best.depth,
+            "best_move": best.best_move.map(|best_move| best_move.col()),
+            "score": best.score,
+            "nodes": best.nodes,
+            "moves": move_scores
+        })))
+    }
+}
+
+
+/// `Api` served over HTTP.
+pub struct HttpServer {
+    server: Server,
+    api: Arc<Api>
+}
+
+impl HttpServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, String> {
+        let server = Server::http(addr).map_err(|e| e.to_string())?;
+        Ok(Self { server, api: Arc::new(Api::new()) })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.server
+            .server_addr()
+            .to_ip()
+            .ok_or(String::from("Server is not listening on an IP address"))
+    }
+
+    /// Serves requests forever, each on its own thread.
+    pub fn run(self) -> Result<(), String> {
+        for mut request in self.server.incoming_requests() {
+            let api = Arc::clone(&self.api);
+            thread::spawn(move || {
+                let mut body = String::new();
+                let (status, json) = match request.as_reader().read_to_string(&mut body) {
+                    Ok(_) => {
+                        let method = match request.method() {
+                            Method::Get => "GET",
+                            Method::Post => "POST",
+                            Method::Put => "PUT",
+                            Method::Delete => "DELETE",
+                            _ => "OTHER"
+                        };
+                        api.handle(method, request.url(), &body)
+                    },
+                    Err(e) => (400, json!({ "error": e.to_string() }))
+                };
+                let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+                let response = Response::from_string(json.to_string())
+                    .with_status_code(status)
+                    .with_header(header);
+                let _ = request.respond(response);
+            });
+        }
+        Ok(())
+    }
+}
+
+
+pub fn run_http_server(addr: &str) -> Result<(), String> {
+    let server = HttpServer::bind(addr)?;
+    println!("Serving the HTTP API on http://{}", server.local_addr()?);
+    server.run()
+}
+
+
+#[cfg(test)]
+mod http_api_tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn game_lifecycle() {
+        let api = Api::new();
+        let (status, game) = api.handle("POST", "/games", "");
+        assert_eq!(status, 201);
+        assert_eq!(game["id"], 1);
+        assert_eq!(game["status"], "in_progress");
+
+        for col in [0, 1, 0, 1, 0, 1] {
+            let (status, _) = api.handle("POST", "/games/1/moves", &format!("{{\"column\": {}}}", col));
+            assert_eq!(status, 200);
+        }
+        let (_, state) = api.handle("GET", "/games/1", "");
+        assert_eq!(state["moves"], "010101");
+        assert_eq!(state["next_player"], 1);
+        assert_eq!(state["board"][5], "12.....");
+
+        let (_, analysis) = api.handle("GET", "/games/1/analysis?depth=2", "");
+        assert_eq!(analysis["best_move"], 0);
+        assert_eq!(analysis["moves"].as_array().unwrap().len(), 7);
+
+        let (status, state) = api.handle("POST", "/games/1/ai-move?depth=2", "");
+        assert_eq!(status, 200);
+        assert_eq!(state["status"], "won");
+        assert_eq!(state["winner"], 1);
+
+        api.handle("POST", "/games", "");
+        let (_, finished) = api.handle("GET", "/games?status=finished", "");
+        assert_eq!(finished.as_array().unwrap().len(), 1);
+        let (_, all) = api.handle("GET", "/games", "");
+        assert_eq!(all.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn invalid_input_is_rejected() {
+        let api = Api::new();
+        api.handle("POST", "/games", "");
+        assert_eq!(api.handle("POST", "/games/1/moves", "not json").0, 400);
+        assert_eq!(api.handle("POST", "/games/1/moves", "{\"column\": -1}").0, 400);
+        assert_eq!(api.handle("POST", "/games/1/moves", "{\"column\": 7}").0, 422);
+        assert_eq!(api.handle("POST", "/games/2/moves", "{\"column\": 3}").0, 404);
+        assert_eq!(api.handle("GET", "/games/abc", "").0, 400);
+        assert_eq!(api.handle("GET", "/games/1/analysis?depth=99", "").0, 400);
+        assert_eq!(api.handle("DELETE", "/games/1", "").0, 405);
+        assert_eq!(api.handle("GET", "/players", "").0, 404);
+        let (_, error) = api.handle("GET", "/games?status=lost", "");
+        assert_eq!(error["error"], "Unknown status filter \"lost\"");
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+               method, path, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn serves_over_http() {
+        let server = HttpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        assert_eq!(request(addr, "POST", "/games", "").0, 201);
+        let (status, state) = request(addr, "POST", "/games/1/moves", "{\"column\": 3}");
+        assert_eq!(status, 200);
+        assert_eq!(state["moves"], "3");
+        assert_eq!(request(addr, "GET", "/games/9", "").0, 404);
+    }
+}
@@ -0,0 +1,27 @@
+pub mod analysis;
+pub mod win_condition_strategy;
+pub mod player;
+pub mod game;
+pub mod board;
+pub mod clock;
+pub mod engine;
+pub mod events;
+#[cfg(feature = "http")]
+pub mod http_api;
+pub mod network;
+pub mod opening_book;
+pub mod perft;
+pub mod protocol;
+pub mod record;
+pub mod render;
+pub mod replay;
+pub mod series;
+pub mod transposition_table;
+pub mod tui;
+pub mod utils;
+pub mod variant;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(test)]
+mod properties;
@@ -1,21 +1,211 @@
-mod win_condition_strategy;
-mod player;
-mod game;
-mod board;
-mod utils;
+use std::path::Path;
 
+use connect_four_rs::analysis::AnalysisObserver;
+use connect_four_rs::board::{parse_cells, Board, Topology};
+use connect_four_rs::clock::TimeControl;
+use connect_four_rs::engine::DEFAULT_SEARCH_DEPTH;
+use connect_four_rs::events::ConsoleObserver;
+use connect_four_rs::game::Game;
+use connect_four_rs::network::{run_client, run_server, DEFAULT_ADDRESS};
+use connect_four_rs::opening_book::{self, OpeningBook, DEFAULT_BOOK_PATH};
+use connect_four_rs::record::{GameRecord, RecordObserver};
+use connect_four_rs::replay::{run_replay, Replay};
+use connect_four_rs::series::{MatchLength, MatchSeries, StartOrder};
+use connect_four_rs::perft::{count_positions, perft};
+use connect_four_rs::tui::run_tui;
+use connect_four_rs::variant::Variant;
 
-use crate::board::Board;
-use game::Game;
+
+fn run_book_command(args: &[String]) -> Result<(), String> {
+    match args {
+        [command, path, ply, rest @ ..] if command == "generate" => {
+            let max_ply = ply.parse::<u8>().map_err(|e| e.to_string())?;
+            let depth = match rest.first() {
+                Some(depth) => depth.parse::<u8>().map_err(|e| e.to_string())?,
+                None => DEFAULT_SEARCH_DEPTH
+            };
+            println!("Generating opening book up to ply {} at depth {}...", max_ply, depth);
+            let book = OpeningBook::generate(max_ply, depth);
+            book.save(path)?;
+            println!("Wrote {} positions to {}", book.len(), path);
+            Ok(())
+        },
+        [command, path, rest @ ..] if command == "inspect" => {
+            let book = OpeningBook::load(path)?;
+            let moves = rest.first().map(String::as_str).unwrap_or("");
+            print!("{}", book.inspect(moves)?);
+            Ok(())
+        },
+        _ => Err(String::from("Usage: book generate <file> <ply> [depth] | book inspect <file> [moves]"))
+    }
+}
+
+
+fn run_perft_command(args: &[String]) -> Result<(), String> {
+    let depth = args
+        .first()
+        .ok_or(String::from("Usage: perft <depth>"))?
+        .parse::<u8>()
+        .map_err(|e| e.to_string())?;
+    let players = Game::generate_simulation_players();
+    let strategies = Game::setup_win_condition_strategies();
+
+    let positions = count_positions(&players, &strategies, depth);
+    for d in 0..=depth {
+        let start = std::time::Instant::now();
+        let nodes = perft(&Board::new(), &players, &strategies, d);
+        println!("depth {:>2}: {:>14} games {:>12} positions ({:.2?})", d, nodes, positions[d as usize], start.elapsed());
+    }
+    Ok(())
+}
+
+
+fn run_replay_command(args: &[String]) -> Result<(), String> {
+    let source = args.first().ok_or(String::from("Usage: replay <file | moves> [game number]"))?;
+    let replay = if Path::new(source).exists() {
+        let records = GameRecord::read_file(source)?;
+        let number = match args.get(1) {
+            Some(number) => number.parse::<usize>().map_err(|e| e.to_string())?,
+            None => records.len()
+        };
+        let record = number
+            .checked_sub(1)
+            .and_then(|index| records.get(index))
+            .ok_or(format!("{} holds {} games", source, records.len()))?;
+        Replay::from_record(record, DEFAULT_SEARCH_DEPTH)?
+    } else {
+        Replay::from_moves(source, DEFAULT_SEARCH_DEPTH)?
+    };
+    run_replay(replay)
+}
+
+fn run_match_command(game: &mut Game, args: &[String]) -> Result<(), String> {
+    let usage = || String::from("Usage: match <games> [best-of] [random]");
+    let games = args.first().and_then(|games| games.parse::<u32>().ok()).filter(|games| *games > 0).ok_or_else(usage)?;
+    let mut length = MatchLength::Games(games);
+    let mut start_order = StartOrder::Alternate;
+    for option in &args[1..] {
+        match option.as_str() {
+            "best-of" => length = MatchLength::BestOf(games),
+            "random" => start_order = StartOrder::Random,
+            _ => return Err(usage())
+        }
+    }
+    let players = game.players().to_vec();
+    MatchSeries::new(length, start_order, &players).run(game)
+}
+
+
+/// Removes `name` and its value from `args`, `Err` with `usage` when the value is missing.
+fn take_option(args: &mut Vec<String>, name: &str, usage: &str) -> Result<Option<String>, String> {
+    let Some(position) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    let value = args.get(position + 1).cloned().ok_or(format!("Usage: {} {}", name, usage))?;
+    args.drain(position..=position + 1);
+    Ok(Some(value))
+}
+
+
+/// Board for `--players`, `--size <rows>x<cols>` and `--win <length>`.
+fn board_from_options(player_count: usize, size: Option<String>, win_length: Option<String>) -> Result<Board, String> {
+    let default = Board::for_players(player_count);
+    let (rows, cols) = match size {
+        Some(size) => {
+            let (rows, cols) = size.split_once('x').ok_or(format!("Invalid size \"{}\", expected <rows>x<cols>", size))?;
+            (rows.parse::<u8>().map_err(|e| e.to_string())?, cols.parse::<u8>().map_err(|e| e.to_string())?)
+        },
+        None => (default.get_rows(), default.get_cols())
+    };
+    let win_length = match win_length {
+        Some(win_length) => win_length.parse::<u8>().map_err(|e| e.to_string())?,
+        None => default.win_length()
+    };
+    Board::with_size(rows, cols, win_length)
+}
 
 
 fn main() -> Result<(), String> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let time_control = match take_option(&mut args, "--clock", "<seconds>[+<increment> | b<periods>x<seconds>]")? {
+        Some(control) => Some(TimeControl::parse(&control)?),
+        None => None
+    };
+    let player_count = match take_option(&mut args, "--players", "<2-4>")? {
+        Some(count) => count.parse::<usize>().map_err(|e| e.to_string())?,
+        None => 2
+    };
+    let size = take_option(&mut args, "--size", "<rows>x<cols>")?;
+    let win_length = take_option(&mut args, "--win", "<length>")?;
+    let variant = match take_option(&mut args, "--variant", "standard | popout | pop10 | misere")? {
+        Some(variant) => Variant::parse(&variant)?,
+        None => Variant::Standard
+    };
+    let topology = match take_option(&mut args, "--topology", "flat | cylinder")? {
+        Some(topology) => Topology::parse(&topology)?,
+        None => Topology::Flat
+    };
+    let blockers = match take_option(&mut args, "--blockers", "<row>:<col>,...")? {
+        Some(cells) => parse_cells(&cells)?,
+        None => vec![]
+    };
+    if let Some("book") = args.first().map(String::as_str) {
+        return run_book_command(&args[1..]);
+    }
+    if let Some("perft") = args.first().map(String::as_str) {
+        return run_perft_command(&args[1..]);
+    }
+    if let Some("replay") = args.first().map(String::as_str) {
+        return run_replay_command(&args[1..]);
+    }
+    if let Some("serve") = args.first().map(String::as_str) {
+        return run_server(args.get(1).map(String::as_str).unwrap_or(DEFAULT_ADDRESS));
+    }
+    if let Some("connect") = args.first().map(String::as_str) {
+        let addr = args.get(1).map(String::as_str).unwrap_or(DEFAULT_ADDRESS);
+        return run_client(addr, args.get(2).map(String::as_str).unwrap_or("guest"));
+    }
+    #[cfg(feature = "http")]
+    if let Some("http") = args.first().map(String::as_str) {
+        return connect_four_rs::http_api::run_http_server(args.get(1).map(String::as_str).unwrap_or("127.0.0.1:8080"));
+    }
+    #[cfg(feature = "websocket")]
+    if let Some("live") = args.first().map(String::as_str) {
+        use connect_four_rs::websocket::{run_websocket_server, DEFAULT_WEBSOCKET_ADDRESS};
+        return run_websocket_server(args.get(1).map(String::as_str).unwrap_or(DEFAULT_WEBSOCKET_ADDRESS));
+    }
+
+    if let Ok(book) = OpeningBook::load(DEFAULT_BOOK_PATH) {
+        opening_book::install(book);
+    }
+
+    let mut board = board_from_options(player_count, size, win_length)?;
+    board.set_variant(variant);
+    board.set_topology(topology);
+    for (row, col) in blockers {
+        board.set_blocked(row, col)?;
+    }
     let mut game = Game::new(
-        Board::new(),
-        Game::generate_players(),
+        board,
+        Game::generate_players_for(player_count)?,
         Game::setup_win_condition_strategies()
     );
     
+    game.set_time_control(time_control);
+    if let Some("tui") = args.first().map(String::as_str) {
+        return run_tui(game);
+    }
+    game.add_observer(ConsoleObserver);
+    if player_count == 2 {
+        game.add_observer(AnalysisObserver::default()); // scores only compare two players
+    }
+    if let Some("record") = args.first().map(String::as_str) {
+        let path = args.get(1).ok_or(String::from("Usage: record <file>"))?;
+        game.add_observer(RecordObserver::new(path));
+    }
+    if let Some("match") = args.first().map(String::as_str) {
+        return run_match_command(&mut game, &args[1..]);
+    }
     game.run()?;
     Ok(())
 }
@@ -0,0 +1,576 @@
+//! Two player games over TCP.
+//!
+//! The protocol is line based, one message per line, fields separated by spaces.
+//!
+//! Client to server:
+//! - `JOIN <name>`: take a free seat, answered with `WELCOME` or `ERROR`
+//! - `MOVE <col>`: drop a disc, only accepted on your turn
+//! - `STATE`: ask for the current `STATE`
+//! - `REMATCH`: vote for a new game once the current one is over, it starts when both voted
+//! - `QUIT`: leave the game
+//!
+//! Server to client:
+//! - `WELCOME <player id> <marker>`: you joined as this player
+//! - `START`: both seats are taken, the game begins
+//! - `MOVED <player id> <row> <col>`: a disc was dropped
+//! - `STATE <moves> <next player id>`: moves played so far as column digits (`-` when none)
+//! - `GAME_OVER WIN <player id>` | `GAME_OVER DRAW` | `GAME_OVER DISCONNECT <player id>`
+//! - `OPPONENT_LEFT`: the other seat is free again, a new `JOIN` starts a new game once the last one is over
+//! - `ERROR <message>`
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::board::Board;
+use crate::game::{Game, GameStatus};
+use crate::player::{Player, PlayerType};
+use crate::utils::get_cli_input;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:4444";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ClientMessage {
+    Join(String),
+    Move(u8),
+    State,
+    Rematch,
+    Quit
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GameOverReason {
+    Win(u8),
+    Draw,
+    Disconnect(u8)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ServerMessage {
+    Welcome(u8, char),
+    Start,
+    Moved(u8, u8, u8),
+    State(String, u8),
+    GameOver(GameOverReason),
+    OpponentLeft,
+    Error(String)
+}
+
+
+fn parse_number(field: Option<&str>, line: &str) -> Result<u8, String> {
+    field
+        .ok_or(format!("Missing argument in \"{}\"", line))?
+        .parse::<u8>()
+        .map_err(|e| format!("{} in \"{}\"", e, line))
+}
+
+
+impl ClientMessage {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("JOIN") => {
+                let name = fields.collect::<Vec<_>>().join(" ");
+                if name.is_empty() {
+                    return Err(String::from("JOIN needs a name"));
+                }
+                Ok(ClientMessage::Join(name))
+            },
+            Some("MOVE") => Ok(ClientMessage::Move(parse_number(fields.next(), line)?)),
+            Some("STATE") => Ok(ClientMessage::State),
+            Some("REMATCH") => Ok(ClientMessage::Rematch),
+            Some("QUIT") => Ok(ClientMessage::Quit),
+            _ => Err(format!("Unknown command \"{}\"", line.trim()))
+        }
+    }
+}
+
+impl fmt::Display for ClientMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientMessage::Join(name) => write!(f, "JOIN {}", name),
+            ClientMessage::Move(col) => write!(f, "MOVE {}", col),
+            ClientMessage::State => write!(f, "STATE"),
+            ClientMessage::Rematch => write!(f, "REMATCH"),
+            ClientMessage::Quit => write!(f, "QUIT")
+        }
+    }
+}
+
+
+impl ServerMessage {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("WELCOME") => {
+                let id = parse_number(fields.next(), line)?;
+                let marker = fields.next().and_then(|m| m.chars().next()).ok_or(format!("Missing marker in \"{}\"", line))?;
+                Ok(ServerMessage::Welcome(id, marker))
+            },
+            Some("START") => Ok(ServerMessage::Start),
+            Some("MOVED") => Ok(ServerMessage::Moved(
+                parse_number(fields.next(), line)?,
+                parse_number(fields.next(), line)?,
+                parse_number(fields.next(), line)?
+            )),
+            Some("STATE") => {
+                let moves = fields.next().ok_or(format!("Missing moves in \"{}\"", line))?;
+                let moves = if moves == "-" { String::new() } else { moves.to_string() };
+                Ok(ServerMessage::State(moves, parse_number(fields.next(), line)?))
+            },
+            Some("GAME_OVER") => match fields.next() {
+                Some("WIN") => Ok(ServerMessage::GameOver(GameOverReason::Win(parse_number(fields.next(), line)?))),
+                Some("DRAW") => Ok(ServerMessage::GameOver(GameOverReason::Draw)),
+                Some("DISCONNECT") => Ok(ServerMessage::GameOver(GameOverReason::Disconnect(parse_number(fields.next(), line)?))),
+                _ => Err(format!("Unknown game over reason in \"{}\"", line))
+            },
+            Some("OPPONENT_LEFT") => Ok(ServerMessage::OpponentLeft),
+            Some("ERROR") => Ok(ServerMessage::Error(fields.collect::<Vec<_>>().join(" "))),
+            _ => Err(format!("Unknown message \"{}\"", line.trim()))
+        }
+    }
+}
+
+impl fmt::Display for ServerMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerMessage::Welcome(id, marker) => write!(f, "WELCOME {} {}", id, marker),
+            ServerMessage::Start => write!(f, "START"),
+            ServerMessage::Moved(id, row, col) => write!(f, "MOVED {} {} {}", id, row, col),
+            ServerMessage::State(moves, next) => {
+                write!(f, "STATE {} {}", if moves.is_empty() { "-" } else { moves }, next)
+            },
+            ServerMessage::GameOver(GameOverReason::Win(id)) => write!(f, "GAME_OVER WIN {}", id),
+            ServerMessage::GameOver(GameOverReason::Draw) => write!(f, "GAME_OVER DRAW"),
+            ServerMessage::GameOver(GameOverReason::Disconnect(id)) => write!(f, "GAME_OVER DISCONNECT {}", id),
+            ServerMessage::OpponentLeft => write!(f, "OPPONENT_LEFT"),
+            ServerMessage::Error(message) => write!(f, "ERROR {}", message)
+        }
+    }
+}
+
+
+/// One line based connection, used by both ends.
+#[derive(Debug)]
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Self, String> {
+        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+        Ok(Self { reader: BufReader::new(stream), writer })
+    }
+
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, String> {
+        Connection::new(TcpStream::connect(addr).map_err(|e| e.to_string())?)
+    }
+
+    pub fn send<M: fmt::Display>(&mut self, message: &M) -> Result<(), String> {
+        writeln!(self.writer, "{}", message).map_err(|e| e.to_string())
+    }
+
+    /// Next line, `None` once the other side has closed the connection.
+    pub fn receive_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end().to_string())
+        }
+    }
+
+    pub fn receive(&mut self) -> Option<Result<ServerMessage, String>> {
+        self.receive_line().map(|line| ServerMessage::parse(&line))
+    }
+
+    pub fn try_clone(&self) -> Result<Self, String> {
+        let stream = self.writer.try_clone().map_err(|e| e.to_string())?;
+        Connection::new(stream)
+    }
+}
+
+
+#[derive(Debug)]
+struct Seat {
+    name: String,
+    stream: TcpStream
+}
+
+#[derive(Debug)]
+struct ServerState {
+    game: Game,
+    seats: Vec<Option<Seat>>,
+    rematch_votes: Vec<bool>,
+    aborted: bool
+}
+
+impl ServerState {
+    fn send_to(&mut self, seat: usize, message: &ServerMessage) {
+        if let Some(seat) = self.seats[seat].as_mut() {
+            let _ = writeln!(seat.stream, "{}", message);
+        }
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for seat in 0..self.seats.len() {
+            self.send_to(seat, message);
+        }
+    }
+
+    fn state_message(&self) -> ServerMessage {
+        let moves: String = self.game.moves().iter().map(|col| col.to_string()).collect();
+        ServerMessage::State(moves, self.game.current_player().id)
+    }
+
+    fn is_over(&self) -> bool {
+        self.aborted || self.game.status() != GameStatus::InProgress
+    }
+}
+
+
+/// Hosts a single `Game` for two remote players.
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<Mutex<ServerState>>
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| e.to_string())?;
+        let players = vec![
+            Player::new(1, "player-one", PlayerType::HUMAN, 'x'),
+            Player::new(2, "player-two", PlayerType::HUMAN, 'o'),
+        ];
+        let seats = players.iter().map(|_| None).collect();
+        let rematch_votes = vec![false; players.len()];
+        let game = Game::new(Board::new(), players, Game::setup_win_condition_strategies());
+        Ok(Self {
+            listener,
+            state: Arc::new(Mutex::new(ServerState { game, seats, rematch_votes, aborted: false }))
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    /// Accepts connections forever, every client is served on its own thread.
+    pub fn run(self) -> Result<(), String> {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let state = Arc::clone(&self.state);
+            thread::spawn(move || handle_client(stream, state));
+        }
+        Ok(())
+    }
+}
+
+
+fn handle_client(stream: TcpStream, state: Arc<Mutex<ServerState>>) {
+    let Ok(mut connection) = Connection::new(stream) else { return };
+    let mut seat: Option<usize> = None;
+
+    while let Some(line) = connection.receive_line() {
+        let message = match ClientMessage::parse(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = connection.send(&ServerMessage::Error(e));
+                continue;
+            }
+        };
+        let mut state = state.lock().unwrap();
+        match (message, seat) {
+            (ClientMessage::Join(_), Some(_)) => {
+                let _ = connection.send(&ServerMessage::Error(String::from("Already joined")));
+            },
+            (ClientMessage::Join(name), None) => {
+                let Some(free) = state.seats.iter().position(Option::is_none) else {
+                    let _ = connection.send(&ServerMessage::Error(String::from("Game is full")));
+                    continue;
+                };
+                let Ok(stream) = connection.writer.try_clone() else { break };
+                println!("{} joined as player {}", name, free + 1);
+                state.seats[free] = Some(Seat { name, stream });
+                seat = Some(free);
+                let player = state.game.players()[free].clone();
+                state.send_to(free, &ServerMessage::Welcome(player.id, player.marker));
+                if state.seats.iter().all(Option::is_some) {
+                    // a game ended by a disconnect is never resumed, the new pair starts over
+                    if state.is_over() {
+                        state.rematch_votes.iter_mut().for_each(|vote| *vote = false);
+                        state.game.restart();
+                        state.aborted = false;
+                    }
+                    state.broadcast(&ServerMessage::Start);
+                    let message = state.state_message();
+                    state.broadcast(&message);
+                }
+            },
+            (_, None) => {
+                let _ = connection.send(&ServerMessage::Error(String::from("Join first")));
+            },
+            (ClientMessage::Move(col), Some(seat)) => {
+                let player = state.game.players()[seat].clone();
+                let error = if state.seats.iter().any(Option::is_none) {
+                    Some(String::from("Waiting for an opponent"))
+                } else if state.is_over() {
+                    Some(String::from("The game is over"))
+                } else if state.game.current_player() != player {
+                    Some(String::from("Not your turn"))
+                } else if !state.game.board().is_valid_move(col) {
+                    Some(format!("Invalid column {}", col))
+                } else {
+                    None
+                };
+                if let Some(error) = error {
+                    state.send_to(seat, &ServerMessage::Error(error));
+                    continue;
+                }
+                match state.game.play_move(col) {
+                    Ok(status) => {
+                        let (row, col) = state.game.last_move().unwrap();
+                        state.broadcast(&ServerMessage::Moved(player.id, row, col));
+                        match status {
+                            GameStatus::Won(winner) => state.broadcast(&ServerMessage::GameOver(GameOverReason::Win(winner.id))),
+                            GameStatus::Draw => state.broadcast(&ServerMessage::GameOver(GameOverReason::Draw)),
+                            GameStatus::InProgress | GameStatus::Aborted => {}
+                        }
+                    },
+                    Err(e) => state.send_to(seat, &ServerMessage::Error(e))
+                }
+            },
+            (ClientMessage::State, Some(seat)) => {
+                let message = state.state_message();
+                state.send_to(seat, &message);
+            },
+            (ClientMessage::Rematch, Some(seat)) => {
+                if !state.is_over() {
+                    state.send_to(seat, &ServerMessage::Error(String::from("The game is not over")));
+                    continue;
+                }
+                state.rematch_votes[seat] = true;
+                if state.rematch_votes.iter().all(|vote| *vote) && state.seats.iter().all(Option::is_some) {
+                    state.rematch_votes.iter_mut().for_each(|vote| *vote = false);
+                    state.game.restart();
+                    state.aborted = false;
+                    state.broadcast(&ServerMessage::Start);
+                    let message = state.state_message();
+                    state.broadcast(&message);
+                }
+            },
+            (ClientMessage::Quit, Some(_)) => break
+        }
+    }
+
+    if let Some(seat) = seat {
+        let mut state = state.lock().unwrap();
+        if let Some(left) = state.seats[seat].take() {
+            println!("{} left the game", left.name);
+        }
+        state.rematch_votes[seat] = false;
+        if !state.is_over() {
+            state.aborted = true;
+            let id = state.game.players()[seat].id;
+            state.broadcast(&ServerMessage::GameOver(GameOverReason::Disconnect(id)));
+        }
+        state.broadcast(&ServerMessage::OpponentLeft);
+    }
+}
+
+
+/// Hosts a game on `addr` until the process is stopped.
+pub fn run_server(addr: &str) -> Result<(), String> {
+    let server = Server::bind(addr)?;
+    println!("Hosting connect four on {}", server.local_addr()?);
+    server.run()
+}
+
+
+/// Joins the game hosted on `addr`, reading moves from the command line.
+pub fn run_client(addr: &str, name: &str) -> Result<(), String> {
+    let mut connection = Connection::connect(addr)?;
+    connection.send(&ClientMessage::Join(name.to_string()))?;
+    let mut incoming = connection.try_clone()?;
+
+    thread::spawn(move || {
+        let players = [
+            Player::new(1, "player-one", PlayerType::HUMAN, 'x'),
+            Player::new(2, "player-two", PlayerType::HUMAN, 'o'),
+        ];
+        let mut board = Board::new();
+        let mut my_id = 0;
+        while let Some(message) = incoming.receive() {
+            match message {
+                Ok(ServerMessage::Welcome(id, marker)) => {
+                    my_id = id;
+                    println!("Joined as player {} ({})", id, marker);
+                },
+                Ok(ServerMessage::Start) => {
+                    board.clear();
+                    println!("Game started!");
+                },
+                Ok(ServerMessage::Moved(id, row, col)) => {
+                    if let Some(player) = players.iter().find(|player| player.id == id) {
+                        board.drop_piece(col, player);
+                    }
+                    println!("Player {} moved (row, column): ({}, {})", id, row, col);
+                    println!("{}", board);
+                    if id != my_id {
+                        print!("Your move: ");
+                        let _ = std::io::stdout().flush();
+                    }
+                },
+                Ok(ServerMessage::State(moves, next)) => {
+                    board = Board::new();
+                    let _ = board.play_moves(&moves, &players);
+                    println!("{}", board);
+                    if next == my_id {
+                        print!("Your move: ");
+                        let _ = std::io::stdout().flush();
+                    }
+                },
+                Ok(ServerMessage::GameOver(reason)) => {
+                    match reason {
+                        GameOverReason::Win(id) if id == my_id => println!("You won!"),
+                        GameOverReason::Win(id) => println!("Player {} won!", id),
+                        GameOverReason::Draw => println!("It's a draw!"),
+                        GameOverReason::Disconnect(id) => println!("Player {} disconnected", id)
+                    }
+                    println!("Type 'rematch' to play again or 'quit' to leave");
+                },
+                Ok(ServerMessage::OpponentLeft) => println!("Your opponent left, waiting for someone to join..."),
+                Ok(ServerMessage::Error(e)) => println!("Server: {}", e),
+                Err(e) => println!("Unreadable message from server: {}", e)
+            }
+        }
+        println!("Connection closed");
+        std::process::exit(0);
+    });
+
+    loop {
+        let input = get_cli_input().trim().to_lowercase();
+        let message = match input.as_str() {
+            "quit" | "q" => ClientMessage::Quit,
+            "rematch" => ClientMessage::Rematch,
+            "state" => ClientMessage::State,
+            "" => continue,
+            col => match col.parse::<u8>() {
+                Ok(col) => ClientMessage::Move(col),
+                Err(_) => {
+                    println!("Enter a column, 'rematch', 'state' or 'quit'");
+                    continue;
+                }
+            }
+        };
+        connection.send(&message)?;
+        if message == ClientMessage::Quit {
+            return Ok(());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod network_tests {
+    use super::*;
+
+    fn start_server() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    fn join(addr: SocketAddr, name: &str) -> Connection {
+        let mut connection = Connection::connect(addr).unwrap();
+        connection.send(&ClientMessage::Join(name.to_string())).unwrap();
+        connection
+    }
+
+    fn expect(connection: &mut Connection, expected: ServerMessage) {
+        assert_eq!(connection.receive(), Some(Ok(expected)));
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            ServerMessage::Welcome(1, 'x'),
+            ServerMessage::Moved(2, 5, 3),
+            ServerMessage::State(String::new(), 1),
+            ServerMessage::State(String::from("3344"), 1),
+            ServerMessage::GameOver(GameOverReason::Disconnect(2)),
+            ServerMessage::Error(String::from("Not your turn")),
+        ];
+        for message in messages {
+            assert_eq!(ServerMessage::parse(&message.to_string()), Ok(message));
+        }
+        assert_eq!(ClientMessage::parse("JOIN henk one"), Ok(ClientMessage::Join(String::from("henk one"))));
+        assert!(ClientMessage::parse("MOVE x").is_err());
+        assert!(ClientMessage::parse("JUMP").is_err());
+    }
+
+    #[test]
+    fn two_clients_play_a_game() {
+        let addr = start_server();
+        let mut one = join(addr, "one");
+        expect(&mut one, ServerMessage::Welcome(1, 'x'));
+        let mut two = join(addr, "two");
+        expect(&mut two, ServerMessage::Welcome(2, 'o'));
+        for connection in [&mut one, &mut two] {
+            expect(connection, ServerMessage::Start);
+            expect(connection, ServerMessage::State(String::new(), 1));
+        }
+
+        two.send(&ClientMessage::Move(0)).unwrap();
+        expect(&mut two, ServerMessage::Error(String::from("Not your turn")));
+        one.send(&ClientMessage::Move(9)).unwrap();
+        expect(&mut one, ServerMessage::Error(String::from("Invalid column 9")));
+
+        for (ply, col) in [0, 1, 0, 1, 0, 1, 0].into_iter().enumerate() {
+            let (mover, id) = if ply % 2 == 0 { (&mut one, 1) } else { (&mut two, 2) };
+            mover.send(&ClientMessage::Move(col)).unwrap();
+            let row = 5 - (ply / 2) as u8;
+            expect(&mut one, ServerMessage::Moved(id, row, col));
+            expect(&mut two, ServerMessage::Moved(id, row, col));
+        }
+        expect(&mut one, ServerMessage::GameOver(GameOverReason::Win(1)));
+        expect(&mut two, ServerMessage::GameOver(GameOverReason::Win(1)));
+
+        one.send(&ClientMessage::Rematch).unwrap();
+        two.send(&ClientMessage::Rematch).unwrap();
+        expect(&mut one, ServerMessage::Start);
+        expect(&mut one, ServerMessage::State(String::new(), 1));
+    }
+
+    #[test]
+    fn disconnect_ends_the_game() {
+        let addr = start_server();
+        let mut one = join(addr, "one");
+        expect(&mut one, ServerMessage::Welcome(1, 'x'));
+        let mut two = join(addr, "two");
+        expect(&mut two, ServerMessage::Welcome(2, 'o'));
+        expect(&mut one, ServerMessage::Start);
+        expect(&mut one, ServerMessage::State(String::new(), 1));
+        one.send(&ClientMessage::Move(3)).unwrap();
+        expect(&mut one, ServerMessage::Moved(1, 5, 3));
+
+        two.send(&ClientMessage::Quit).unwrap();
+        expect(&mut one, ServerMessage::GameOver(GameOverReason::Disconnect(2)));
+        expect(&mut one, ServerMessage::OpponentLeft);
+
+        one.send(&ClientMessage::Move(4)).unwrap();
+        expect(&mut one, ServerMessage::Error(String::from("Waiting for an opponent")));
+
+        // a new player takes the free seat and gets a fresh game, the aborted one is not resumed
+        let mut three = join(addr, "three");
+        expect(&mut three, ServerMessage::Welcome(2, 'o'));
+        expect(&mut three, ServerMessage::Start);
+        expect(&mut three, ServerMessage::State(String::new(), 1));
+        expect(&mut one, ServerMessage::Start);
+        expect(&mut one, ServerMessage::State(String::new(), 1));
+    }
+}
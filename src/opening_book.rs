@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+use rayon::prelude::*;
+
+use crate::board::{zobrist_key, Board};
+use crate::engine::Engine;
+use crate::game::Game;
+use crate::player::{Player, PlayerType};
+use crate::transposition_table::TranspositionTable;
+
+pub const DEFAULT_BOOK_PATH: &str = "opening_book.bin";
+const MAGIC: &[u8; 4] = b"C4BK";
+const VERSION: u8 = 2;
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 11;
+
+static INSTALLED_BOOK: OnceLock<OpeningBook> = OnceLock::new();
+
+/// Best move and engine score (from the side to move's point of view) of a canonical position.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BookEntry {
+    pub best_move: u8,
+    pub score: i16
+}
+
+/// Book entries sorted by `book_key`, stored on disk as
+/// a 12 byte header followed by 11 bytes per entry (key, move, score; little endian).
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OpeningBook {
+    max_ply: u8,
+    entries: Vec<(u64, BookEntry)>
+}
+
+
+/// Makes `book` available to computer players, only the first call has an effect.
+pub fn install(book: OpeningBook) {
+    let _ = INSTALLED_BOOK.set(book);
+}
+
+pub fn installed() -> Option<&'static OpeningBook> {
+    INSTALLED_BOOK.get()
+}
+
+
+impl OpeningBook {
+    /// Searches every position reachable in fewer than `max_ply` moves to `depth`.
+    pub fn generate(max_ply: u8, depth: u8) -> Self {
+        let players = book_players();
+        let mut positions = HashMap::new();
+        collect_positions(&mut Board::new(), &players, max_ply, &mut positions);
+
+        let mut entries: Vec<(u64, BookEntry)> = positions
+            .into_par_iter()
+            .map_init(
+                || Engine::with_table(depth, TranspositionTable::new(1 << 16)),
+                |engine, (_, board)| {
+                    let player_index = board.disc_count() as usize % players.len();
+                    let result = engine.search(&board, &players, player_index);
+                    result.best_move.map(|best_move| (book_key(&board, &players[player_index]), BookEntry {
+                        best_move: board.canonical_move(best_move.col()),
+                        score: result.score
+                    }))
+                })
+            .flatten()
+            .collect();
+        entries.sort_by_key(|(key, _)| *key);
+        Self { max_ply, entries }
+    }
+
+    pub fn max_ply(&self) -> u8 {
+        self.max_ply
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entry for `board` with `to_move` to move, the move translated back from the canonical position.
+    /// Books only cover the standard board, with player 1 having moved first.
+    pub fn entry(&self, board: &Board, to_move: &Player) -> Option<BookEntry> {
+        if !board.has_standard_rules() {
+            return None;
+        }
+        let index = self.entries
+            .binary_search_by_key(&book_key(board, to_move), |(key, _)| *key)
+            .ok()?;
+        let entry = self.entries[index].1;
+        Some(BookEntry { best_move: board.canonical_move(entry.best_move), ..entry })
+    }
+
+    pub fn lookup(&self, board: &Board, to_move: &Player) -> Option<u8> {
+        self.entry(board, to_move)
+            .map(|entry| entry.best_move)
+            .filter(|col| board.is_valid_move(*col))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.entries.len() * ENTRY_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(Board::new().get_rows());
+        bytes.push(Board::new().get_cols());
+        bytes.push(self.max_ply);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (key, entry) in self.entries.iter() {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.push(entry.best_move);
+            bytes.extend_from_slice(&entry.score.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err(String::from("Not an opening book file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("Unsupported opening book version {}", bytes[4]));
+        }
+        let board = Board::new();
+        if bytes[5] != board.get_rows() || bytes[6] != board.get_cols() {
+            return Err(format!("Opening book is for a {} by {} board", bytes[5], bytes[6]));
+        }
+        let count = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        if bytes.len() != HEADER_SIZE + count * ENTRY_SIZE {
+            return Err(String::from("Opening book file is truncated"));
+        }
+        let entries = bytes[HEADER_SIZE..]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| (
+                u64::from_le_bytes(chunk[0..8].try_into().unwrap()),
+                BookEntry {
+                    best_move: chunk[8],
+                    score: i16::from_le_bytes(chunk[9..11].try_into().unwrap())
+                }
+            ))
+            .collect();
+        Ok(Self { max_ply: bytes[7], entries })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Book entry of every position along `moves`, as printed by `book inspect`.
+    pub fn inspect(&self, moves: &str) -> Result<String, String> {
+        let players = book_players();
+        let mut board = Board::new();
+        let mut report = String::new();
+        if let Some(c) = moves.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(format!("Invalid move '{}' in \"{}\"", c, moves)); // plies are sliced by byte below
+        }
+        for ply in 0..=moves.len() {
+            board.play_moves(&moves[ply.saturating_sub(1)..ply], &players)?;
+            let to_move = &players[board.disc_count() as usize % players.len()];
+            let line = match self.entry(&board, to_move) {
+                Some(entry) => format!("best move {}, score {}", entry.best_move, entry.score),
+                None => String::from("not in book")
+            };
+            report.push_str(&format!("{:>3} {:<12} {:016x} {}\n", ply, &moves[..ply], book_key(&board, to_move), line));
+        }
+        report.push_str(&board.to_string());
+        Ok(report)
+    }
+}
+
+
+/// The book is generated for player ids 1 and 2, matching `Game::generate_players`.
+fn book_players() -> Vec<Player> {
+    vec![
+        Player::new(1, "book-one", PlayerType::COMPUTER, 'x'),
+        Player::new(2, "book-two", PlayerType::COMPUTER, 'o'),
+    ]
+}
+
+
+/// Key of `board` with `to_move` to move, the same for mirrored positions.
+fn book_key(board: &Board, to_move: &Player) -> u64 {
+    board.canonical_key() ^ zobrist_key(u8::MAX, u8::MAX, to_move.id)
+}
+
+
+fn collect_positions(board: &mut Board, players: &[Player], max_ply: u8, positions: &mut HashMap<u64, Board>) {
+    if board.disc_count() >= max_ply || positions.contains_key(&board.canonical_key()) {
+        return;
+    }
+    let canonical = if board.is_canonical() { board.clone() } else { board.mirror() };
+    positions.insert(board.canonical_key(), canonical);
+
+    let player = &players[board.disc_count() as usize % players.len()];
+    for col in 0..board.get_cols() {
+        if board.drop_piece(col, player).is_none() {
+            continue;
+        }
+        let game_over = Game::setup_win_condition_strategies()
+            .iter()
+            .any(|strategy| strategy.has_won(player, board));
+        if !game_over {
+            collect_positions(board, players, max_ply, positions);
+        }
+        board.undo_move(col);
+    }
+}
+
+
+#[cfg(test)]
+mod opening_book_tests {
+    use super::*;
+
+    #[test]
+    fn generated_book_covers_mirrored_positions() {
+        let book = OpeningBook::generate(2, 2);
+        // 1 empty board + 4 distinct first moves once mirrors are merged
+        assert_eq!(book.len(), 5);
+
+        let players = book_players();
+        let mut left = Board::new();
+        left.play_moves("1", &players).unwrap();
+        let mut right = Board::new();
+        right.play_moves("5", &players).unwrap();
+
+        let left_move = book.lookup(&left, &players[1]).unwrap();
+        assert_eq!(book.lookup(&right, &players[1]), Some(left.mirror_col(left_move)));
+        assert_eq!(book.lookup(&Board::new(), &players[0]), Some(3));
+        assert_eq!(book.lookup(&Board::with_size(6, 8, 4).unwrap(), &players[0]), None);
+    }
+
+    #[test]
+    fn book_is_skipped_when_the_second_seat_moves_first() {
+        let book = OpeningBook::generate(3, 2);
+        let players = book_players();
+        let mut board = Board::new();
+        assert_eq!(book.lookup(&board, &players[1]), None);
+        board.drop_piece(3, &players[1]);
+        assert_eq!(book.lookup(&board, &players[0]), None);
+        board.drop_piece(2, &players[0]);
+        assert_eq!(book.lookup(&board, &players[1]), None);
+        // the same discs with player 1 to move are in the book
+        assert!(book.lookup(&board, &players[0]).is_some());
+    }
+
+    #[test]
+    fn book_survives_serialization() {
+        let book = OpeningBook::generate(2, 2);
+        let restored = OpeningBook::from_bytes(&book.to_bytes()).unwrap();
+        assert_eq!(book, restored);
+        assert_eq!(restored.max_ply(), 2);
+
+        assert!(OpeningBook::from_bytes(b"nope").is_err());
+        let bytes = book.to_bytes();
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut resized = bytes.clone();
+        resized[6] = 8;
+        assert_eq!(OpeningBook::from_bytes(&resized), Err(String::from("Opening book is for a 6 by 8 board")));
+    }
+
+    #[test]
+    fn inspect_rejects_anything_but_columns() {
+        let book = OpeningBook::generate(2, 2);
+        assert!(book.inspect("33").unwrap().starts_with("  0"));
+        assert_eq!(book.inspect("3é"), Err(String::from("Invalid move 'é' in \"3é\"")));
+    }
+}
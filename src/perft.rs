@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use rayon::prelude::*;
+
+use crate::board::Board;
+use crate::player::Player;
+use crate::win_condition_strategy::WinConditionStrategy;
+
+/// Number of move sequences of exactly `depth` plies from `board`, where sequences end early
+/// (and are not counted) once a player has won. `players` take turns starting from the one
+/// whose turn it is by disc count.
+pub fn perft(board: &Board,
+             players: &[Player],
+             win_condition_strategies: &[WinConditionStrategy],
+             depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let player = &players[board.disc_count() as usize % players.len()];
+
+    // split the work over the first move, deeper levels are too small to be worth a thread
+    (0..board.get_cols())
+        .into_par_iter()
+        .map(|col| {
+            let mut board = board.clone();
+            if board.drop_piece(col, player).is_none() {
+                return 0;
+            }
+            if has_won(player, &board, win_condition_strategies) {
+                return if depth == 1 { 1 } else { 0 };
+            }
+            perft_sequential(&mut board, players, win_condition_strategies, depth - 1)
+        })
+        .sum()
+}
+
+
+fn perft_sequential(board: &mut Board,
+                    players: &[Player],
+                    win_condition_strategies: &[WinConditionStrategy],
+                    depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let player = &players[board.disc_count() as usize % players.len()];
+    let mut nodes = 0;
+    for col in 0..board.get_cols() {
+        if board.drop_piece(col, player).is_none() {
+            continue;
+        }
+        if !has_won(player, board, win_condition_strategies) {
+            nodes += perft_sequential(board, players, win_condition_strategies, depth - 1);
+        } else if depth == 1 {
+            nodes += 1;
+        }
+        board.undo_move(col);
+    }
+    nodes
+}
+
+
+/// Number of distinct positions reachable at each ply from 0 to `depth`, like
+/// OEIS A212693. Won positions are counted but not played on.
+pub fn count_positions(players: &[Player],
+                       win_condition_strategies: &[WinConditionStrategy],
+                       depth: u8) -> Vec<u64> {
+    let mut counts = vec![1];
+    let mut frontier = vec![Board::new()];
+
+    for _ in 0..depth {
+        let next: HashMap<u64, (Board, bool)> = frontier
+            .par_iter()
+            .flat_map_iter(|board| {
+                let player = &players[board.disc_count() as usize % players.len()];
+                (0..board.get_cols()).filter_map(move |col| {
+                    let mut child = board.clone();
+                    child.drop_piece(col, player)?;
+                    let game_over = has_won(player, &child, win_condition_strategies);
+                    Some((child.hash(), (child, game_over)))
+                })
+            })
+            .collect();
+        counts.push(next.len() as u64);
+        frontier = next
+            .into_values()
+            .filter(|(_, game_over)| !game_over)
+            .map(|(board, _)| board)
+            .collect();
+    }
+    counts
+}
+
+
+fn has_won(player: &Player, board: &Board, win_condition_strategies: &[WinConditionStrategy]) -> bool {
+    win_condition_strategies
+        .iter()
+        .any(|strategy| strategy.has_won(player, board))
+}
+
+
+#[cfg(test)]
+mod perft_tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn perft_matches_reference_counts() {
+        let players = Game::generate_simulation_players();
+        let strategies = Game::setup_win_condition_strategies();
+        // depth 7 is 7^7 less the 7 sequences overfilling a column, depth 8 no longer plays on after wins at ply 7
+        let expected = [1, 7, 49, 343, 2401, 16807, 117649, 823536, 5673234];
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&Board::new(), &players, &strategies, depth as u8), *nodes);
+        }
+    }
+
+    #[test]
+    fn position_counts_match_reference_counts() {
+        let players = Game::generate_simulation_players();
+        let strategies = Game::setup_win_condition_strategies();
+        // OEIS A212693
+        assert_eq!(count_positions(&players, &strategies, 7), vec![1, 7, 49, 238, 1120, 4263, 16422, 54859]);
+    }
+
+    #[test]
+    fn perft_stops_at_wins() {
+        let players = Game::generate_simulation_players();
+        let strategies = Game::setup_win_condition_strategies();
+        let mut b = Board::new();
+        b.play_moves("001122", &players).unwrap();
+        // column 3 wins for the first player, the other 6 moves each allow 7 replies
+        assert_eq!(perft(&b, &players, &strategies, 1), 7);
+        assert_eq!(perft(&b, &players, &strategies, 2), 6 * 7);
+    }
+}
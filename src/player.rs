@@ -1,89 +1,256 @@
 use std::fmt;
-use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use rand::*;
 use rand::seq::SliceRandom;
 
 use crate::board::Board;
+use crate::engine::Engine;
+use crate::opening_book;
+use crate::protocol::{ExternalEngine, GoLimits};
 use crate::utils::*;
+use crate::variant::Move;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum PlayerType {
     HUMAN,
-    COMPUTER
+    COMPUTER,
+    AI(u8), // search depth
+    ENGINE(Arc<str>) // command starting an engine speaking the `protocol` module's protocol
 }
 
+/// What a player does on their turn.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PlayerAction {
+    Move(u8),
+    Pop(u8), // removes the player's own disc from the bottom of a column
+    Capture(u8), // Pop 10: pops a disc that is part of a line and keeps it
+    Return(u8, u8), // Pop 10: pops a disc from the first column and drops it into the second
+    Resign,
+    OfferDraw, // accepts the draw when another player offered it first
+    Abort
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Player {
     pub id: u8,
     pub player_type: PlayerType,
-    pub name: &'static str,
+    pub name: Arc<str>,
     pub marker: char
 }
 
 
 impl Player {
     
-    pub fn new(id: u8, name: &'static str, player_type: PlayerType, marker: char) -> Self {
-        Self {id, name, player_type, marker}
+    /// Id `u8::MAX` is reserved for the blockers on obstacle boards.
+    pub fn new(id: u8, name: &str, player_type: PlayerType, marker: char) -> Self {
+        assert_ne!(id, u8::MAX, "Player id {} is reserved", u8::MAX);
+        Self {id, name: Arc::from(name), player_type, marker}
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self { 
             id: 0, 
-            name: "", 
+            name: Arc::from(""), 
             player_type: PlayerType::COMPUTER, 
             marker: ' ' 
         }
     }
 
-    pub fn cli_ask_desired_move(&self, board: &Board) -> Result<u8, String> {
-        let input = get_cli_input()
-            .trim()
-            .parse::<u8>()
-            .map_err(|e| e.to_string());
-        
-        match input {
-            Ok(value) => {
-                if board.is_valid_move(value) {
-                    Ok(value)
-                } else {
-                    println!("Invalid column {}. Please choose another column", value);
-                    self.cli_ask_desired_move(board)
-                }
-            },
-            Err(e) => {
-                println!("Error while receiving input: {}", e);
-                self.cli_ask_desired_move(board)
+    /// A move as written by `Move`, or `resign`, `draw` or `abort` typed on the command line,
+    /// `time_left` is the clock time of the player.
+    pub fn cli_ask_desired_move(&self, board: &Board, time_left: Option<Duration>) -> Result<PlayerAction, String> {
+        let deadline = time_left.map(|time_left| Instant::now() + time_left);
+        loop {
+            let line = match deadline {
+                Some(deadline) => get_cli_input_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .ok_or(String::from("Out of time"))?,
+                None => get_cli_input()
+            };
+            match PlayerAction::parse(&line) {
+                Ok(PlayerAction::Move(value)) if !board.is_valid_move(value) => {
+                    println!("Invalid column {}. Please choose another column", value)
+                },
+                Ok(PlayerAction::Pop(value)) if !board.can_pop(value, self) => {
+                    println!("You cannot pop column {}. Please choose another move", value)
+                },
+                Ok(action @ (PlayerAction::Capture(_) | PlayerAction::Return(..)))
+                    if !action.to_move().is_some_and(|mv| board.is_legal(mv, self)) => {
+                    println!("You cannot play {}. Please choose another move", action)
+                },
+                Ok(action) => return Ok(action),
+                Err(e) => println!("Error while receiving input: {}", e)
             }
         }
     }
 
-    pub fn generate_move(&self, board: &Board) -> Result<u8, String> {
-        if let Some(available_cols) = board.get_available_cols() {
-            let mut rng = thread_rng();
-            if let Some(chosen_col) = available_cols.choose(&mut rng) {
-                Ok(*chosen_col)
-            } else {
-                println!("Could not generate move");
-                Err(String::from("Could not generate move"))
-            }
+    /// A random legal move, pops included when the variant allows them.
+    pub fn generate_move(&self, board: &Board) -> Result<Move, String> {
+        let mut rng = thread_rng();
+        if let Some(chosen) = board.legal_moves(self).choose(&mut rng) {
+            Ok(*chosen)
         } else {
             println!("No available columns");
             Err(String::from("No available columns"))
         }
     }
 
-    pub fn get_move(&self, board: &Board) -> Result<u8, String> {
-        println!("What move would you like to play?");
-        match self.player_type {
-            PlayerType::HUMAN => self.cli_ask_desired_move(board),
-            PlayerType::COMPUTER => self.generate_move(board),
-            _ => Err(format!("Unknown player type: {:?}", self.player_type))
+    /// Opening book move if the position is in the installed book and only two players are left,
+    /// otherwise an engine search that stops after `time_limit`.
+    pub fn generate_best_move(&self,
+                              board: &Board,
+                              players: &[Player],
+                              depth: u8,
+                              time_limit: Option<Duration>) -> Result<Move, String> {
+        if players.len() == 2
+            && let Some(book_move) = opening_book::installed().and_then(|book| book.lookup(board, self)) {
+            return Ok(Move::Drop(book_move));
+        }
+        let player_index = players
+            .iter()
+            .position(|player| player.id == self.id)
+            .ok_or(format!("Player {} is not part of this game", self.name))?;
+        let mut engine = Engine::new(depth);
+        engine.set_time_limit(time_limit);
+        engine
+            .search(board, players, player_index)
+            .best_move
+            .ok_or(String::from("No available columns"))
+    }
+
+    /// An engine player takes a draw unless its search sees a win.
+    pub fn accepts_draw(&self, board: &Board, players: &[Player], depth: u8) -> bool {
+        players
+            .iter()
+            .position(|player| player.id == self.id)
+            .is_some_and(|player_index| Engine::new(depth).search(board, players, player_index).score <= 0)
+    }
+
+    /// Asks the player's external engine process for its move, telling it about a draw offer first.
+    pub fn request_engine_move(&self,
+                               board: &Board,
+                               players: &[Player],
+                               engine: &mut ExternalEngine,
+                               limits: &GoLimits,
+                               draw_offered: bool) -> Result<PlayerAction, String> {
+        let (action, info) = engine.best_move(board, players, self, limits, draw_offered)?;
+        if let Some(last_info) = info.last() {
+            println!("{}: {}", engine.name, last_info);
+        }
+        Ok(action)
+    }
+
+    /// Next action, `limits` holds the clock times when the game is played with a clock,
+    /// `draw_offered` tells whether another player offered a draw and `engine` is the process of an `ENGINE` player.
+    pub fn get_move(&self,
+                    board: &Board,
+                    players: &[Player],
+                    limits: &GoLimits,
+                    draw_offered: bool,
+                    engine: Option<&mut ExternalEngine>) -> Result<PlayerAction, String> {
+        if draw_offered {
+            println!("{} is offered a draw, type draw to accept", self.name);
+        }
+        if board.is_popping() {
+            println!("What move would you like to play? (c<column> to keep a disc in a line, \
+                      p<column>><column> to drop a popped disc back, or resign, draw, abort)");
+        } else if board.variant().allows_pop() {
+            println!("What move would you like to play? (p<column> to pop, or resign, draw, abort)");
+        } else {
+            println!("What move would you like to play? (or resign, draw, abort)");
+        }
+        let player_index = players.iter().position(|player| player.id == self.id).unwrap_or(0);
+        match &self.player_type {
+            PlayerType::HUMAN => self.cli_ask_desired_move(board, limits.time_left(player_index)),
+            PlayerType::COMPUTER => self.generate_move(board).map(PlayerAction::from),
+            PlayerType::AI(depth) if draw_offered && self.accepts_draw(board, players, *depth) => Ok(PlayerAction::OfferDraw),
+            PlayerType::AI(depth) => {
+                self.generate_best_move(board, players, *depth, limits.time_budget(player_index)).map(PlayerAction::from)
+            },
+            PlayerType::ENGINE(command) => {
+                let engine = engine.ok_or(format!("Engine {} was not started", command))?;
+                self.request_engine_move(board, players, engine, limits, draw_offered)
+            },
         }
     }
     
 }
 
+impl PlayerAction {
+    /// A move as written by `Move`, `resign`, `draw` or `abort`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            "resign" => Ok(PlayerAction::Resign),
+            "draw" => Ok(PlayerAction::OfferDraw),
+            "abort" => Ok(PlayerAction::Abort),
+            mv => Move::parse(mv).map(PlayerAction::from)
+        }
+    }
+
+    /// The move this action makes, `None` for resigning, draws and aborting.
+    pub fn to_move(&self) -> Option<Move> {
+        match self {
+            PlayerAction::Move(col) => Some(Move::Drop(*col)),
+            PlayerAction::Pop(col) => Some(Move::Pop(*col)),
+            PlayerAction::Capture(col) => Some(Move::Capture(*col)),
+            PlayerAction::Return(from, to) => Some(Move::Return(*from, *to)),
+            PlayerAction::Resign | PlayerAction::OfferDraw | PlayerAction::Abort => None
+        }
+    }
+}
+
+impl From<Move> for PlayerAction {
+    fn from(mv: Move) -> Self {
+        match mv {
+            Move::Drop(col) => PlayerAction::Move(col),
+            Move::Pop(col) => PlayerAction::Pop(col),
+            Move::Capture(col) => PlayerAction::Capture(col),
+            Move::Return(from, to) => PlayerAction::Return(from, to)
+        }
+    }
+}
+
+impl fmt::Display for PlayerAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayerAction::Move(col) => write!(f, "{}", col),
+            PlayerAction::Pop(col) => write!(f, "p{}", col),
+            PlayerAction::Capture(col) => write!(f, "c{}", col),
+            PlayerAction::Return(from, to) => write!(f, "p{}>{}", from, to),
+            PlayerAction::Resign => write!(f, "resign"),
+            PlayerAction::OfferDraw => write!(f, "draw"),
+            PlayerAction::Abort => write!(f, "abort")
+        }
+    }
+}
+
+impl PlayerType {
+    /// Reads the `Display` form back.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        match text.split_once(' ').unwrap_or((text, "")) {
+            ("human", "") => Ok(PlayerType::HUMAN),
+            ("computer", "") => Ok(PlayerType::COMPUTER),
+            ("ai", depth) => Ok(PlayerType::AI(depth.parse::<u8>().map_err(|e| format!("Invalid depth \"{}\": {}", depth, e))?)),
+            ("engine", command) if !command.is_empty() => Ok(PlayerType::ENGINE(Arc::from(command))),
+            _ => Err(format!("Unknown player type \"{}\"", text))
+        }
+    }
+}
+
+impl fmt::Display for PlayerType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayerType::HUMAN => write!(f, "human"),
+            PlayerType::COMPUTER => write!(f, "computer"),
+            PlayerType::AI(depth) => write!(f, "ai {}", depth),
+            PlayerType::ENGINE(command) => write!(f, "engine {}", command)
+        }
+    }
+}
+
 impl fmt::Display for Player {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.marker)
@@ -92,9 +259,16 @@ impl fmt::Display for Player {
 
 
 #[cfg(test)]
+#[allow(unused_mut, unused_variables, unused_assignments, clippy::field_reassign_with_default)]
 mod player_tests {
     use super::*;
     
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn blocker_id_is_reserved() {
+        Player::new(u8::MAX, "impostor", PlayerType::COMPUTER, '#');
+    }
+
     #[test]
     fn out_of_bound_move_rejected() {
         let mut b = Board::generate_full_board();
@@ -102,5 +276,23 @@ mod player_tests {
         p.marker = 'x';
         assert_eq!(b.apply_gravity(b.get_cols()), None);
     }
+
+    #[test]
+    fn player_types_round_trip() {
+        for player_type in [PlayerType::HUMAN, PlayerType::COMPUTER, PlayerType::AI(8), PlayerType::ENGINE(Arc::from("./engine --fast"))] {
+            assert_eq!(PlayerType::parse(&player_type.to_string()), Ok(player_type));
+        }
+        assert!(PlayerType::parse("ai deep").is_err());
+        assert!(PlayerType::parse("engine").is_err());
+    }
+
+    #[test]
+    fn actions_round_trip() {
+        for action in [PlayerAction::Move(3), PlayerAction::Pop(2), PlayerAction::Capture(1), PlayerAction::Return(4, 0), PlayerAction::Resign, PlayerAction::OfferDraw, PlayerAction::Abort] {
+            assert_eq!(PlayerAction::parse(&action.to_string()), Ok(action));
+        }
+        assert_eq!(PlayerAction::parse(" 6\n"), Ok(PlayerAction::Move(6)));
+        assert!(PlayerAction::parse("quit").is_err());
+    }
     
 }
\ No newline at end of file
@@ -0,0 +1,161 @@
+//! Property based tests over random legal games.
+
+use proptest::prelude::*;
+
+use crate::board::{Board, Cell};
+use crate::game::{Game, GameStatus};
+use crate::player::Player;
+
+#[derive(Debug)]
+struct PlayedGame {
+    game: Game,
+    moves: String
+}
+
+impl PlayedGame {
+    fn board(&self) -> &Board {
+        self.game.board()
+    }
+
+    fn winner(&self) -> Option<Player> {
+        match self.game.status() {
+            GameStatus::Won(player) => Some(player),
+            _ => None
+        }
+    }
+}
+
+
+/// Plays `choices` as indices into the available columns on a real game, until it is over.
+fn play_game(choices: &[usize]) -> PlayedGame {
+    let mut game = Game::create_simulated_game();
+    let mut moves = String::new();
+
+    for choice in choices {
+        if game.status() != GameStatus::InProgress {
+            break;
+        }
+        let available = game.board().get_available_cols().expect("a game in progress has a move");
+        let col = available[choice % available.len()];
+        game.play_move(col).expect("available columns are legal");
+        moves.push_str(&col.to_string());
+    }
+    PlayedGame { game, moves }
+}
+
+fn winners(board: &Board) -> Vec<Player> {
+    let strategies = Game::setup_win_condition_strategies();
+    Game::generate_simulation_players()
+        .into_iter()
+        .filter(|player| strategies.iter().any(|strategy| strategy.has_won(player, board)))
+        .collect()
+}
+
+fn random_game() -> impl Strategy<Value = PlayedGame> {
+    prop::collection::vec(0..7usize, 0..=42).prop_map(|choices| play_game(&choices))
+}
+
+
+proptest! {
+    #[test]
+    fn no_floating_pieces(game in random_game()) {
+        let b = game.board();
+        for col in 0..b.get_cols() as usize {
+            for row in 1..b.get_rows() as usize {
+                if b.cells()[row - 1][col] != Cell::Empty {
+                    prop_assert_ne!(&b.cells()[row][col], &Cell::Empty);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn disc_counts_alternate(game in random_game()) {
+        let players = Game::generate_simulation_players();
+        let count = |player: &Player| game.board().cells().iter().flatten().filter(|cell| cell.is_disc_of(player)).count();
+        let first = count(&players[0]);
+        let second = count(&players[1]);
+        prop_assert!(first == second || first == second + 1);
+        prop_assert_eq!(first + second, game.moves.len());
+        prop_assert_eq!(game.board().disc_count() as usize, game.moves.len());
+    }
+
+    #[test]
+    fn at_most_one_winner(game in random_game()) {
+        let winners = winners(game.board());
+        prop_assert!(winners.len() <= 1);
+        prop_assert_eq!(winners.first().cloned(), game.winner());
+    }
+
+    #[test]
+    fn game_stops_at_first_win(game in random_game()) {
+        if let Some(winner) = game.winner() {
+            let players = Game::generate_simulation_players();
+            let last_mover = players[(game.moves.len() - 1) % players.len()].clone();
+            prop_assert_eq!(winner, last_mover);
+
+            let mut before = game.board().clone();
+            let last_col = game.moves.chars().last().unwrap().to_digit(10).unwrap() as u8;
+            before.undo_move(last_col);
+            prop_assert!(winners(&before).is_empty());
+        }
+    }
+
+    #[test]
+    fn draw_only_when_full(game in random_game()) {
+        let b = game.board();
+        if game.game.status() == GameStatus::Draw {
+            prop_assert!(b.is_full() && winners(b).is_empty());
+        }
+        if b.is_full() {
+            prop_assert_eq!(game.moves.len(), (b.get_rows() * b.get_cols()) as usize);
+            prop_assert_ne!(game.game.status(), GameStatus::InProgress);
+        }
+    }
+
+    #[test]
+    fn turns_alternate(game in random_game()) {
+        let players = Game::generate_simulation_players();
+        prop_assert_eq!(game.game.moves().len(), game.moves.len());
+        prop_assert_eq!(&game.game.current_player(), &players[game.moves.len() % players.len()]);
+    }
+
+    #[test]
+    fn finished_games_refuse_moves(game in random_game()) {
+        let mut game = game;
+        let status = game.game.status();
+        if status != GameStatus::InProgress {
+            for col in 0..game.board().get_cols() {
+                prop_assert!(game.game.play_move(col).is_err());
+            }
+            prop_assert_eq!(game.game.status(), status);
+            prop_assert_eq!(game.game.moves().len(), game.moves.len());
+        }
+    }
+
+    #[test]
+    fn representations_agree(game in random_game()) {
+        let b = game.board();
+        prop_assert_eq!(b.hash(), b.compute_hash());
+        prop_assert_eq!(b.mirrored_hash(), b.mirror().compute_hash());
+        let mirrored_twice = b.mirror().mirror();
+        prop_assert_eq!(mirrored_twice.cells(), b.cells());
+
+        let mut replayed = Board::new();
+        replayed.play_moves(&game.moves, &Game::generate_simulation_players()).unwrap();
+        prop_assert_eq!(replayed.cells(), b.cells());
+        prop_assert_eq!(replayed.hash(), b.hash());
+    }
+
+    #[test]
+    fn undo_restores_every_position(game in random_game()) {
+        let mut b = game.board().clone();
+        for c in game.moves.chars().rev() {
+            let col = c.to_digit(10).unwrap() as u8;
+            prop_assert!(b.undo_move(col).is_some());
+            prop_assert_eq!(b.hash(), b.compute_hash());
+        }
+        prop_assert_eq!(b.hash(), 0);
+        prop_assert_eq!(b.disc_count(), 0);
+    }
+}
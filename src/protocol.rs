@@ -0,0 +1,590 @@
+//! Text protocol between a game and an engine process, in the spirit of chess' UCI.
+//!
+//! The game writes commands to the engine's stdin, one per line:
+//! - `cfp`: start of the session, answered with `id name <name>`, `id author <author>` and `cfpok`
+//! - `isready`: answered with `readyok`
+//! - `newgame`: forget everything about the previous game, answered with `readyok` once the engine is ready
+//! - `variant standard | popout | pop10 | misere`: rules of the positions that follow, standard after `newgame`
+//! - `topology flat | cylinder`: whether lines of the positions that follow wrap around from the last column to the
+//!   first, flat after `newgame`
+//! - `size <rows> <cols> <win length>`: board of the positions that follow, `6 7 4` after `newgame`
+//! - `players <ids>`: players still in the game in turn order separated by `/`, e.g. `1/2/3`, `1/2` after `newgame`.
+//!   Discs of other numbers belong to players who are out of the game
+//! - `position startpos [moves <columns>]`: the position after the given moves, e.g. `moves 3344`
+//! - `position board <rows> [tomove <n>] [captured <counts>]`: rows from top to bottom separated by `/`, `.` for
+//!   empty cells, `#` for blockers and the player number for discs, e.g. `......./......./......./......./......./...1...`.
+//!   Without `tomove` the player to move follows from the number of discs. `captured` gives the Pop 10 discs collected
+//!   by every player in order, separated by `/`, e.g. `3/1`
+//! - `drawoffer`: the opponent offers a draw, valid for the next `go`
+//! - `go [depth <n>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [time<n> <ms>] [inc<n> <ms>]`:
+//!   `time<n>` and `inc<n>` give the clock of the n-th player of `players`, `wtime`/`winc` and `btime`/`binc` are
+//!   the same as `time1`/`inc1` and `time2`/`inc2`
+//! - `quit`
+//!
+//! The engine answers `go` with any number of `info depth <n> score <s> nodes <n> time <ms> pv <col>`
+//! lines followed by `bestmove <col>`, `bestmove p<col>` to pop, `bestmove c<col>` or `bestmove p<col>><col>` in
+//! Pop 10, `bestmove resign` or `bestmove draw` to offer or accept a draw.
+//! Unknown commands are ignored.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, Cell, Topology};
+use crate::engine::{Engine, DEFAULT_SEARCH_DEPTH};
+use crate::game::Game;
+use crate::player::{Player, PlayerAction, PlayerType};
+use crate::variant::Variant;
+
+const ENGINE_NAME: &str = "connect_four_rs";
+const MAX_DEPTH: u8 = 42;
+/// Share of the remaining clock time spent on one move.
+const MOVES_TO_GO: u32 = 20;
+/// Players a `go` command can give clock times for, as many as player numbers.
+const MAX_SEATS: usize = 9;
+
+/// Search limits of a `go` command.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct GoLimits {
+    pub depth: Option<u8>,
+    pub movetime: Option<Duration>,
+    pub times: [Option<Duration>; MAX_SEATS], // clock time of every player in turn order
+    pub increments: [Option<Duration>; MAX_SEATS]
+}
+
+impl GoLimits {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut limits = GoLimits::default();
+        let fields: Vec<&str> = args.split_whitespace().collect();
+        for pair in fields.chunks(2) {
+            let [name, value] = pair else {
+                return Err(format!("Missing value for {}", pair[0]));
+            };
+            let number = value.parse::<u64>().map_err(|e| format!("{} for {}", e, name))?;
+            let millis = Some(Duration::from_millis(number));
+            let seat = |prefix: &str| name
+                .strip_prefix(prefix)
+                .and_then(|seat| seat.parse::<usize>().ok())
+                .filter(|seat| (1..=MAX_SEATS).contains(seat));
+            match (*name, seat("time"), seat("inc")) {
+                ("depth", ..) => limits.depth = Some(number.min(u8::MAX as u64) as u8),
+                ("movetime", ..) => limits.movetime = millis,
+                ("wtime", ..) => limits.times[0] = millis,
+                ("btime", ..) => limits.times[1] = millis,
+                ("winc", ..) => limits.increments[0] = millis,
+                ("binc", ..) => limits.increments[1] = millis,
+                (_, Some(seat), _) => limits.times[seat - 1] = millis,
+                (_, _, Some(seat)) => limits.increments[seat - 1] = millis,
+                _ => return Err(format!("Unknown go parameter {}", name))
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Clock time left for player `player_index`, `movetime` when no clock times are given.
+    pub fn time_left(&self, player_index: usize) -> Option<Duration> {
+        self.times.get(player_index).copied().flatten().or(self.movetime)
+    }
+
+    /// Time to spend on a move for player `player_index` (0 moves first, like white in chess).
+    pub fn time_budget(&self, player_index: usize) -> Option<Duration> {
+        if self.movetime.is_some() {
+            return self.movetime;
+        }
+        let increment = self.increments.get(player_index).copied().flatten().unwrap_or_default();
+        self.times.get(player_index).copied().flatten().map(|time| time / MOVES_TO_GO + increment / 2)
+    }
+}
+
+impl std::fmt::Display for GoLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "go")?;
+        if let Some(depth) = self.depth {
+            write!(f, " depth {}", depth)?;
+        }
+        if let Some(movetime) = self.movetime {
+            write!(f, " movetime {}", movetime.as_millis())?;
+        }
+        // the first two players keep the names engines for two players know
+        for (kind, times, first, second) in [("time", &self.times, "wtime", "btime"), ("inc", &self.increments, "winc", "binc")] {
+            for (seat, time) in times.iter().enumerate() {
+                let Some(time) = time else {
+                    continue;
+                };
+                match seat {
+                    0 => write!(f, " {} {}", first, time.as_millis())?,
+                    1 => write!(f, " {} {}", second, time.as_millis())?,
+                    _ => write!(f, " {}{} {}", kind, seat + 1, time.as_millis())?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Board in the `position board` notation.
+pub fn board_to_rows(board: &Board) -> String {
+    (0..board.get_rows())
+        .map(|row| (0..board.get_cols())
+            .map(|col| match board.cell(row, col) {
+                Cell::Empty => '.',
+                Cell::Blocked => '#',
+                Cell::Disc(player) => char::from(b'0' + player.id)
+            })
+            .collect::<String>())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Fills the empty `board` from the `position board` notation.
+pub fn board_from_rows(rows: &str, mut board: Board, players: &[Player]) -> Result<Board, String> {
+    let rows: Vec<&str> = rows.split('/').collect();
+    if rows.len() != board.get_rows() as usize {
+        return Err(format!("Expected {} rows, got {}", board.get_rows(), rows.len()));
+    }
+    for (row, cells) in rows.iter().enumerate() {
+        if cells.chars().count() != board.get_cols() as usize {
+            return Err(format!("Expected {} cells in row {}", board.get_cols(), row));
+        }
+        for (col, cell) in cells.chars().enumerate() {
+            match cell {
+                '.' => continue,
+                '#' => {
+                    board.set_blocked(row as u8, col as u8)?;
+                    continue;
+                },
+                _ => {}
+            }
+            let id = cell.to_digit(10).filter(|id| *id > 0).ok_or(format!("Unknown player '{}'", cell))? as u8;
+            // discs of players who are out of the game stay on the board
+            let player = players
+                .iter()
+                .find(|player| player.id == id)
+                .cloned()
+                .unwrap_or_else(|| Player::new(id, "", PlayerType::COMPUTER, cell));
+            board.set_cell(row as u8, col as u8, &player);
+        }
+    }
+    Ok(board)
+}
+
+
+/// Engine side of the protocol.
+#[derive(Debug)]
+pub struct EngineSession {
+    board: Board,
+    size: (u8, u8, u8),
+    players: Vec<Player>,
+    variant: Variant,
+    topology: Topology,
+    to_move: Option<usize>,
+    draw_offered: bool
+}
+
+impl Default for EngineSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EngineSession {
+    pub fn new() -> Self {
+        Self {
+            board: Board::new(),
+            size: (Board::new().get_rows(), Board::new().get_cols(), Board::new().win_length()),
+            players: Game::generate_simulation_players(),
+            variant: Variant::Standard,
+            topology: Topology::Flat,
+            to_move: None,
+            draw_offered: false
+        }
+    }
+
+    /// Handles one command, returns false once the session should end.
+    pub fn handle<W: Write>(&mut self, line: &str, out: &mut W) -> Result<bool, String> {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "cfp" => {
+                writeln!(out, "id name {}", ENGINE_NAME).map_err(|e| e.to_string())?;
+                writeln!(out, "id author connect_four_rs contributors").map_err(|e| e.to_string())?;
+                writeln!(out, "cfpok").map_err(|e| e.to_string())?;
+            },
+            "isready" => writeln!(out, "readyok").map_err(|e| e.to_string())?,
+            "newgame" => {
+                *self = Self::new();
+                writeln!(out, "readyok").map_err(|e| e.to_string())?;
+            },
+            "variant" => self.variant = Variant::parse(args)?,
+            "topology" => self.topology = Topology::parse(args)?,
+            "size" => self.size = Self::parse_size(args)?,
+            "players" => self.players = Self::parse_players(args)?,
+            "position" => {
+                self.board = self.parse_position(args)?;
+                self.board.set_variant(self.variant);
+                self.board.set_topology(self.topology);
+            },
+            "drawoffer" => self.draw_offered = true,
+            "go" => self.go(GoLimits::parse(args)?, out)?,
+            "quit" => return Ok(false),
+            _ => {}
+        }
+        out.flush().map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+
+    fn parse_size(args: &str) -> Result<(u8, u8, u8), String> {
+        let numbers = args
+            .split_whitespace()
+            .map(|number| number.parse::<u8>().map_err(|e| format!("Invalid size \"{}\": {}", args, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let [rows, cols, win_length] = numbers[..] else {
+            return Err(format!("Invalid size \"{}\", expected rows, columns and win length", args));
+        };
+        Board::with_size(rows, cols, win_length)?;
+        Ok((rows, cols, win_length))
+    }
+
+    fn parse_players(args: &str) -> Result<Vec<Player>, String> {
+        let players = args
+            .trim()
+            .split('/')
+            .map(|id| match id.parse::<u8>() {
+                Ok(number @ 1..=9) => Ok(Player::new(number, "", PlayerType::COMPUTER, char::from(b'0' + number))),
+                _ => Err(format!("Invalid player \"{}\" in \"{}\"", id, args))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if players.len() < 2 {
+            return Err(format!("Expected at least 2 players in \"{}\"", args));
+        }
+        Ok(players)
+    }
+
+    fn empty_board(&self) -> Board {
+        let (rows, cols, win_length) = self.size;
+        Board::with_size(rows, cols, win_length).unwrap_or_default()
+    }
+
+    fn parse_position(&mut self, args: &str) -> Result<Board, String> {
+        let mut fields = args.split_whitespace();
+        self.to_move = None;
+        match fields.next() {
+            Some("startpos") => {
+                let mut board = self.empty_board();
+                if fields.next() == Some("moves") {
+                    board.play_moves(fields.next().unwrap_or(""), &self.players)?;
+                }
+                Ok(board)
+            },
+            Some("board") => {
+                let mut board = board_from_rows(fields.next().unwrap_or(""), self.empty_board(), &self.players)?;
+                while let Some(name) = fields.next() {
+                    match (name, fields.next()) {
+                        ("tomove", Some(id)) => {
+                            let to_move = self.players.iter().position(|player| id == player.id.to_string());
+                            self.to_move = Some(to_move.ok_or(format!("Unknown player to move in \"{}\"", args))?);
+                        },
+                        ("captured", Some(counts)) => {
+                            for (player, count) in self.players.iter().zip(counts.split('/')) {
+                                board.set_captured(player, count.parse::<u8>().map_err(|e| format!("Invalid captured count: {}", e))?);
+                            }
+                        },
+                        _ => return Err(format!("Unknown position \"{}\"", args))
+                    }
+                }
+                Ok(board)
+            },
+            _ => Err(format!("Unknown position \"{}\"", args))
+        }
+    }
+
+    fn go<W: Write>(&mut self, limits: GoLimits, out: &mut W) -> Result<(), String> {
+        let player_index = self.to_move.unwrap_or(self.board.disc_count() as usize % self.players.len());
+        let budget = limits.time_budget(player_index);
+        let depth = limits.depth.unwrap_or(if budget.is_some() { MAX_DEPTH } else { DEFAULT_SEARCH_DEPTH });
+        let mut engine = Engine::new(depth);
+        engine.set_time_limit(budget);
+
+        let started = Instant::now();
+        let mut write_error = None;
+        let result = engine.search_with_info(&self.board, &self.players, player_index, |info| {
+            let pv = info.best_move.map_or(String::new(), |best_move| format!(" pv {}", best_move));
+            if let Err(e) = writeln!(out, "info depth {} score {} nodes {} time {}{}",
+                                     info.depth, info.score, info.nodes, started.elapsed().as_millis(), pv) {
+                write_error = Some(e.to_string());
+            }
+        });
+        if let Some(e) = write_error {
+            return Err(e);
+        }
+        let draw_offered = std::mem::take(&mut self.draw_offered);
+        match result.best_move {
+            Some(_) if draw_offered && result.score <= 0 => writeln!(out, "bestmove draw"),
+            Some(best_move) => writeln!(out, "bestmove {}", best_move),
+            None => writeln!(out, "bestmove none")
+        }.map_err(|e| e.to_string())
+    }
+}
+
+
+/// Runs an engine session on the given streams until `quit` or end of input.
+pub fn run_engine<R: BufRead, W: Write>(input: R, mut output: W) -> Result<(), String> {
+    let mut session = EngineSession::new();
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        match session.handle(&line, &mut output) {
+            Ok(true) => continue,
+            Ok(false) => break,
+            Err(e) => eprintln!("{}", e)
+        }
+    }
+    Ok(())
+}
+
+
+/// Game side of the protocol: an engine subprocess.
+#[derive(Debug)]
+pub struct ExternalEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    pub name: String
+}
+
+impl ExternalEngine {
+    /// Starts `command` (program and arguments separated by spaces) and performs the handshake.
+    pub fn spawn(command: &str) -> Result<Self, String> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or(String::from("Empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start engine {}: {}", program, e))?;
+        let stdin = child.stdin.take().ok_or(String::from("Engine has no stdin"))?;
+        let stdout = BufReader::new(child.stdout.take().ok_or(String::from("Engine has no stdout"))?);
+        let mut engine = Self { child, stdin, stdout, name: program.to_string() };
+
+        engine.send("cfp")?;
+        loop {
+            let line = engine.receive()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.to_string();
+            }
+            if line == "cfpok" {
+                break;
+            }
+        }
+        Ok(engine)
+    }
+
+    /// Tells the engine a new game begins and waits until it is ready for it.
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.send("newgame")?;
+        while self.receive()? != "readyok" {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command).map_err(|e| e.to_string())?;
+        self.stdin.flush().map_err(|e| e.to_string())
+    }
+
+    fn receive(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        match self.stdout.read_line(&mut line) {
+            Ok(0) => Err(format!("Engine {} exited", self.name)),
+            Ok(_) => Ok(line.trim().to_string()),
+            Err(e) => Err(e.to_string())
+        }
+    }
+
+    /// Asks the engine for the move of `to_move` in `board` where `players` are still in the game,
+    /// `info` lines are returned alongside.
+    pub fn best_move(&mut self,
+                     board: &Board,
+                     players: &[Player],
+                     to_move: &Player,
+                     limits: &GoLimits,
+                     draw_offered: bool) -> Result<(PlayerAction, Vec<String>), String> {
+        self.send(&format!("variant {}", board.variant()))?;
+        self.send(&format!("topology {}", board.topology()))?;
+        self.send(&format!("size {} {} {}", board.get_rows(), board.get_cols(), board.win_length()))?;
+        let ids: Vec<String> = players.iter().map(|player| player.id.to_string()).collect();
+        self.send(&format!("players {}", ids.join("/")))?;
+        let captured = match board.variant() {
+            Variant::Pop10 => {
+                let counts: Vec<String> = players.iter().map(|player| board.captured(player).to_string()).collect();
+                format!(" captured {}", counts.join("/"))
+            },
+            _ => String::new()
+        };
+        self.send(&format!("position board {} tomove {}{}", board_to_rows(board), to_move.id, captured))?;
+        if draw_offered {
+            self.send("drawoffer")?;
+        }
+        self.send(&limits.to_string())?;
+        let mut info = vec![];
+        loop {
+            let line = self.receive()?;
+            if let Some(best_move) = line.strip_prefix("bestmove ") {
+                let action = PlayerAction::parse(best_move)
+                    .map_err(|_| format!("Engine {} sent an invalid move: {}", self.name, best_move))?;
+                return Ok((action, info));
+            }
+            if line.starts_with("info") {
+                info.push(line);
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
+
+
+#[cfg(test)]
+mod protocol_tests {
+    use super::*;
+
+    fn run(commands: &[&str]) -> String {
+        let mut out = Vec::new();
+        run_engine(commands.join("\n").as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn handshake_and_search() {
+        let out = run(&["cfp", "isready", "position startpos moves 001122", "go depth 4", "quit"]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "id name connect_four_rs");
+        assert!(lines.contains(&"cfpok"));
+        assert!(lines.contains(&"readyok"));
+        assert!(lines.iter().any(|line| line.starts_with("info depth 1 ")));
+        assert_eq!(lines.last(), Some(&"bestmove 3"));
+    }
+
+    #[test]
+    fn popout_positions_can_be_searched() {
+        let rows = "......./......./......./1....../2111.../1222...";
+        let out = run(&["variant popout", &format!("position board {} tomove 1", rows), "go depth 2"]);
+        assert_eq!(out.lines().last(), Some("bestmove p0"));
+        let standard = run(&["variant popout", "newgame", &format!("position board {} tomove 1", rows), "go depth 2"]);
+        assert_ne!(standard.lines().last(), Some("bestmove p0"));
+        assert!(run(&["variant sideways"]).is_empty());
+    }
+
+    #[test]
+    fn cylinder_positions_wrap_around() {
+        let rows = "......./......./......./......./......./1..2211";
+        let out = run(&["topology cylinder", &format!("position board {} tomove 1", rows), "go depth 2"]);
+        assert_eq!(out.lines().last(), Some("bestmove 1"));
+        assert!(run(&["topology sphere"]).is_empty());
+    }
+
+    #[test]
+    fn pop10_positions_count_captured_discs() {
+        let rows = "2121212/1212121/2121212/1212121/2121212/1111222";
+        let out = run(&["variant pop10", &format!("position board {} tomove 1 captured 9/3", rows), "go depth 2"]);
+        assert_eq!(out.lines().last(), Some("bestmove c3"));
+        assert!(out.lines().any(|line| line.contains("score 99")));
+        assert!(run(&["variant pop10", &format!("position board {} captured 9/x", rows)]).is_empty());
+    }
+
+    #[test]
+    fn draw_offer_is_taken_in_a_lost_position() {
+        // x threatens both ends of 1 2 3
+        let out = run(&["position startpos moves 11223", "drawoffer", "go depth 4", "go depth 4"]);
+        let best_moves: Vec<&str> = out.lines().filter(|line| line.starts_with("bestmove ")).collect();
+        assert_eq!(best_moves[0], "bestmove draw");
+        assert_ne!(best_moves[1], "bestmove draw");
+    }
+
+    #[test]
+    fn board_position_round_trips() {
+        let players = Game::generate_simulation_players();
+        let mut b = Board::new();
+        b.set_blocked(5, 6).unwrap();
+        b.play_moves("3342", &players).unwrap();
+        let rows = board_to_rows(&b);
+        assert_eq!(rows, "......./......./......./......./...2.../..211.#");
+        assert_eq!(board_from_rows(&rows, Board::new(), &players).unwrap().cells(), b.cells());
+        assert!(board_from_rows("...", Board::new(), &players).is_err());
+
+        let out = run(&[&format!("position board {}", rows), "go depth 2"]);
+        assert!(out.lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn resized_boards_with_more_players_can_be_searched() {
+        // player 3 completes the bottom row of a 5 by 5 board
+        let rows = "...../...../...../...../333.2";
+        let out = run(&["size 5 5 4", "players 1/2/3", &format!("position board {} tomove 3", rows), "go depth 2"]);
+        assert_eq!(out.lines().last(), Some("bestmove 3"));
+        // player 2 is out, their disc stays
+        let out = run(&["size 5 5 4", "players 1/3", &format!("position board {} tomove 3", rows), "go depth 2"]);
+        assert_eq!(out.lines().last(), Some("bestmove 3"));
+        assert!(run(&["size 5 5 6", "players 1", "players 0/1"]).is_empty());
+        // newgame goes back to the standard board
+        let mut session = EngineSession::new();
+        let mut out = Vec::new();
+        session.handle("size 5 5 4", &mut out).unwrap();
+        session.handle("newgame", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "readyok\n");
+        assert!(session.handle(&format!("position board {}", rows), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn newgame_forgets_the_previous_position() {
+        let rows = "......./......./......./......./......./2111...";
+        let out = run(&[&format!("position board {} tomove 2", rows), "drawoffer", "newgame", "position startpos", "go depth 2"]);
+        // player 1 moves on the fresh board and no draw offer is pending
+        assert_eq!(out.lines().last(), Some("bestmove 3"));
+    }
+
+    #[test]
+    fn go_limits_parse_and_budget() {
+        let limits = GoLimits::parse("wtime 60000 btime 30000 winc 1000").unwrap();
+        assert_eq!(limits.time_budget(0), Some(Duration::from_millis(3500)));
+        assert_eq!(limits.time_budget(1), Some(Duration::from_millis(1500)));
+        assert_eq!(GoLimits::parse(&limits.to_string()[3..]), Ok(limits));
+        assert_eq!(GoLimits::parse("movetime 200").unwrap().time_budget(1), Some(Duration::from_millis(200)));
+
+        let limits = GoLimits::parse("time1 60000 btime 30000 time3 20000 inc3 2000").unwrap();
+        assert_eq!(limits.time_budget(2), Some(Duration::from_millis(2000)));
+        assert_eq!((limits.time_left(0), limits.time_left(3)), (Some(Duration::from_secs(60)), None));
+        assert_eq!(limits.to_string(), "go wtime 60000 btime 30000 time3 20000 inc3 2000");
+        assert!(GoLimits::parse("time10 1000").is_err() && GoLimits::parse("time0 1000").is_err());
+        assert!(GoLimits::parse("depth").is_err());
+        assert!(GoLimits::parse("speed 3").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn external_engine_over_pipes() {
+        // a minimal engine that always plays column 4
+        let script = "while read line; do case $line in \
+            cfp) echo 'id name fake'; echo cfpok;; \
+            newgame) echo readyok;; \
+            go*) echo 'info depth 1 score 0 nodes 1 time 0 pv 4'; echo 'bestmove 4';; \
+            quit) exit 0;; esac; done";
+        let path = std::env::temp_dir().join(format!("fake_engine_{}.sh", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+
+        let mut engine = ExternalEngine::spawn(&format!("sh {}", path.display())).unwrap();
+        assert_eq!(engine.name, "fake");
+        engine.new_game().unwrap();
+        let players = Game::generate_simulation_players();
+        let (action, info) = engine.best_move(&Board::new(), &players, &players[0], &GoLimits::default(), true).unwrap();
+        assert_eq!(action, PlayerAction::Move(4));
+        assert_eq!(info.len(), 1);
+        drop(engine);
+        std::fs::remove_file(path).unwrap();
+    }
+}
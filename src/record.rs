@@ -0,0 +1,658 @@
+//! Game records in a PGN like text format.
+//!
+//! A record is a block of `[Name "value"]` headers, an empty line and the move text:
+//!
+//! ```text
+//! [Event "Connect four"]
+//! [Date "2026.10.18"]
+//! [Player1 "henk-one"]
+//! [Player1Type "human"]
+//! [Player1Marker "x"]
+//! [Player2 "henk-two"]
+//! [Player2Type "ai 8"]
+//! [Player2Marker "o"]
+//! [FirstPlayer "1"]
+//! [Rows "6"]
+//! [Columns "7"]
+//! [WinLength "4"]
+//! [Variant "standard"]
+//! [Topology "flat"]
+//! [Blockers "-"]
+//! [TimeControl "-"]
+//! [Result "1-0"]
+//! [Termination "normal"]
+//!
+//! 1. 3 {[%eval 12] the centre} 3 2. 4 4 ... 1-0
+//! ```
+//!
+//! Moves are columns, or `p` and a column for a pop, `c` and a column for a Pop 10 capture and `p<column>><column>` for a
+//! Pop 10 disc popped from the first column and dropped into the second, a move number starts every round. A `{comment}`
+//! belongs to the move before it and may start with an `[%eval <score>]` evaluation, a `}` or `\` inside it is escaped
+//! with a backslash. `R<player>` or `T<player>` between the moves marks the player numbered in the headers resigning or
+//! losing on time there, the others play on without them. The result has a point per player like `1-0`, `0-1-0` or `1/2-1/2`, or is `*` for an unfinished or
+//! aborted game. The termination is one of `normal`, `time forfeit`, `resignation`, `agreement`, `abandoned`,
+//! `repetition` or `unterminated`. `Blockers` lists the cells holding a neutral blocker as `<row>:<col>` separated by
+//! commas, rows counted from the top, or `-` for none. Files hold any number of records separated by empty lines.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::{format_cells, parse_cells, Board, Topology};
+use crate::events::{GameEvent, GameObserver};
+use crate::clock::TimeControl;
+use crate::game::{Elimination, Game, GameStatus, Termination};
+use crate::player::{Player, PlayerType};
+use crate::variant::{Move, Variant};
+use crate::win_condition_strategy::CONNECTED_COMPONENTS_WIN_THRESHOLD;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RecordedMove {
+    pub played: Move,
+    pub eval: Option<i16>,
+    pub comment: Option<String>
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GameRecord {
+    headers: Vec<(String, String)>,
+    pub moves: Vec<RecordedMove>,
+    pub eliminations: Vec<Elimination>
+}
+
+
+/// Today as `YYYY.MM.DD` in UTC.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400) as i64;
+    // civil_from_days from Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+
+fn result_token(game: &Game) -> String {
+    let points: Vec<String> = match game.status() {
+        GameStatus::Won(winner) => game.players()
+            .iter()
+            .map(|player| String::from(if *player == winner { "1" } else { "0" }))
+            .collect(),
+        GameStatus::Draw => vec![format!("1/{}", game.players().len()); game.players().len()],
+        GameStatus::InProgress | GameStatus::Aborted => return String::from("*")
+    };
+    points.join("-")
+}
+
+fn is_result_token(token: &str) -> bool {
+    let points: Vec<&str> = token.split('-').collect();
+    let draw = format!("1/{}", points.len());
+    token == "*" || points.len() >= 2 && (
+        points.iter().all(|point| *point == draw)
+            || points.iter().all(|point| matches!(*point, "0" | "1")) && points.iter().filter(|point| **point == "1").count() == 1
+    )
+}
+
+fn parse_header(line: &str) -> Result<(String, String), String> {
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or(format!("Invalid header \"{}\"", line))?;
+    let (name, value) = inner.split_once(' ').ok_or(format!("Invalid header \"{}\"", line))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or(format!("Header value must be quoted in \"{}\"", line))?;
+    Ok((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Comment up to its closing `}` with `\}` and `\\` unescaped, and the text after it.
+fn parse_comment(text: &str) -> Result<(String, &str), String> {
+    let mut comment = String::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '}' => return Ok((comment, &text[index + 1..])),
+            '\\' if matches!(chars.peek(), Some((_, '}' | '\\'))) => comment.extend(chars.next().map(|(_, escaped)| escaped)),
+            _ => comment.push(c)
+        }
+    }
+    Err(String::from("Unclosed comment"))
+}
+
+
+impl RecordedMove {
+    pub fn new(played: Move) -> Self {
+        Self { played, eval: None, comment: None }
+    }
+}
+
+
+impl GameRecord {
+    /// Headers and moves of `game`, the termination is "normal" once it is over.
+    pub fn from_game(game: &Game) -> Self {
+        let mut record = GameRecord::default();
+        record.set_header("Event", "Connect four");
+        record.set_header("Date", &today());
+        for (n, player) in game.players().iter().enumerate() {
+            record.set_header(&format!("Player{}", n + 1), &player.name);
+            record.set_header(&format!("Player{}Type", n + 1), &player.player_type.to_string());
+            record.set_header(&format!("Player{}Marker", n + 1), &player.marker.to_string());
+        }
+        record.set_header("FirstPlayer", &(game.first_player_index() + 1).to_string());
+        record.set_header("Rows", &game.board().get_rows().to_string());
+        record.set_header("Columns", &game.board().get_cols().to_string());
+        record.set_header("WinLength", &game.board().win_length().to_string());
+        record.set_header("Variant", &game.board().variant().to_string());
+        record.set_header("Topology", &game.board().topology().to_string());
+        record.set_header("Blockers", &format_cells(&game.board().blocked_cells()));
+        let time_control = game.time_control().map_or(String::from("-"), |control| control.to_string());
+        record.set_header("TimeControl", &time_control);
+        record.set_header("Result", &result_token(game));
+        let termination = game.termination().map_or(String::from("unterminated"), |termination| termination.to_string());
+        record.set_header("Termination", &termination);
+        record.moves = game.moves().iter().map(|played| RecordedMove::new(*played)).collect();
+        record.eliminations = game.eliminations().to_vec();
+        record
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the header if present, otherwise adds it at the end.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(header, _)| header == name) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string()))
+        }
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Moves as written by `Move`, for drops only the format of `Board::play_moves`.
+    pub fn moves_string(&self) -> String {
+        self.moves.iter().map(|recorded| recorded.played.to_string()).collect()
+    }
+
+    /// Players described by the `Player<n>` headers, names and engine commands are leaked.
+    pub fn players(&self) -> Result<Vec<Player>, String> {
+        let mut players = vec![];
+        while let Some(name) = self.header(&format!("Player{}", players.len() + 1)) {
+            let n = players.len() + 1;
+            if n >= u8::MAX as usize {
+                return Err(String::from("A record has too many players"));
+            }
+            let player_type = match self.header(&format!("Player{}Type", n)) {
+                Some(player_type) => PlayerType::parse(player_type)?,
+                None => PlayerType::HUMAN
+            };
+            let marker = self
+                .header(&format!("Player{}Marker", n))
+                .and_then(|marker| marker.chars().next())
+                .unwrap_or(if n == 1 { 'x' } else { 'o' });
+            players.push(Player::new(n as u8, name, player_type, marker));
+        }
+        if players.len() < 2 {
+            return Err(String::from("A record needs at least two players"));
+        }
+        Ok(players)
+    }
+
+    /// Index of the player who moved first, from the `FirstPlayer` header.
+    pub fn first_player_index(&self) -> Result<usize, String> {
+        match self.header("FirstPlayer") {
+            None => Ok(0),
+            Some(first) => first
+                .parse::<usize>()
+                .ok()
+                .and_then(|first| first.checked_sub(1))
+                .ok_or(format!("Invalid FirstPlayer \"{}\"", first))
+        }
+    }
+
+    /// Players starting with the one who moved first.
+    pub fn players_in_move_order(&self) -> Result<Vec<Player>, String> {
+        let mut players = self.players()?;
+        let first = self.first_player_index()?;
+        if first >= players.len() {
+            return Err(format!("There is no player {}", first + 1));
+        }
+        players.rotate_left(first);
+        Ok(players)
+    }
+
+    /// Empty board of the size and rules in the `Rows`, `Columns`, `WinLength`, `Variant`, `Topology` and `Blockers`
+    /// headers, standard when missing.
+    pub fn board(&self) -> Result<Board, String> {
+        let size = |name, default: u8| match self.header(name) {
+            Some(value) => value.parse::<u8>().map_err(|_| format!("Invalid {} \"{}\"", name, value)),
+            None => Ok(default)
+        };
+        let standard = Board::new();
+        let mut board = Board::with_size(
+            size("Rows", standard.get_rows())?,
+            size("Columns", standard.get_cols())?,
+            size("WinLength", CONNECTED_COMPONENTS_WIN_THRESHOLD)?
+        )?;
+        board.set_variant(self.header("Variant").map_or(Ok(Variant::Standard), Variant::parse)?);
+        board.set_topology(self.header("Topology").map_or(Ok(Topology::Flat), Topology::parse)?);
+        for (row, col) in self.header("Blockers").map_or(Ok(vec![]), parse_cells)? {
+            board.set_blocked(row, col)?;
+        }
+        Ok(board)
+    }
+
+    /// Game with every recorded move played, ready to be replayed or continued.
+    pub fn to_game(&self) -> Result<Game, String> {
+        let mut game = Game::new(self.board()?, self.players()?, Game::setup_win_condition_strategies());
+        game.set_first_player(self.first_player_index()?)?;
+        for ply in 0..=self.moves.len() {
+            for elimination in self.eliminations.iter().filter(|elimination| elimination.ply == ply) {
+                match elimination.termination {
+                    Termination::TimeForfeit => game.lose_on_time(elimination.player_index),
+                    _ => {
+                        game.resign(elimination.player_index).map_err(|e| format!("Resignation after move {}: {}", ply, e))?;
+                    }
+                }
+            }
+            if let Some(recorded) = self.moves.get(ply) {
+                game.make_move(recorded.played).map_err(|e| format!("Move {}: {}", ply + 1, e))?;
+            }
+        }
+        if let Some(control) = self.header("TimeControl").filter(|control| *control != "-") {
+            game.set_time_control(Some(TimeControl::parse(control)?));
+        }
+        let player_count = game.players().len();
+        match self.header("Termination") {
+            Some(termination @ ("time forfeit" | "resignation")) => {
+                let winner = self.header("Result")
+                    .and_then(|result| result.split('-').position(|point| point == "1"))
+                    .filter(|winner| *winner < player_count)
+                    .ok_or(format!("Invalid result {:?} for a {}", self.header("Result"), termination))?;
+                // records without eliminations only tell how the last player went out, the others are given the same ending
+                while game.status() == GameStatus::InProgress {
+                    let loser = (1..player_count)
+                        .map(|offset| (winner + offset) % player_count)
+                        .find(|index| !game.is_eliminated(*index))
+                        .ok_or(format!("Invalid {}", termination))?;
+                    match termination {
+                        "resignation" => { game.resign(loser)?; },
+                        _ => game.lose_on_time(loser)
+                    }
+                }
+            },
+            Some("agreement") => {
+                for offset in 0..player_count {
+                    let seat = (game.first_player_index() + offset) % player_count;
+                    if !game.is_eliminated(seat) {
+                        game.offer_draw(seat)?;
+                    }
+                }
+            },
+            Some("abandoned") => {
+                game.abort(game.current_player_index())?;
+            },
+            _ => {}
+        }
+        Ok(game)
+    }
+
+    /// A single record.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut records = GameRecord::parse_all(text)?;
+        match records.len() {
+            1 => Ok(records.remove(0)),
+            n => Err(format!("Expected one game record, found {}", n))
+        }
+    }
+
+    /// Every record in `text`, in order.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, String> {
+        let mut records = vec![];
+        let mut record = GameRecord::default();
+        let mut movetext = String::new();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') && !movetext.trim().is_empty() {
+                record.parse_movetext(&movetext)?;
+                records.push(std::mem::take(&mut record));
+                movetext.clear();
+            }
+            if line.starts_with('[') && movetext.trim().is_empty() {
+                let (name, value) = parse_header(line)?;
+                record.set_header(&name, &value);
+            } else {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+        if !record.headers.is_empty() || !movetext.trim().is_empty() {
+            record.parse_movetext(&movetext)?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    fn parse_movetext(&mut self, movetext: &str) -> Result<(), String> {
+        let mut rest = movetext;
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                return Ok(());
+            }
+            if let Some(comment) = rest.strip_prefix('{') {
+                let (comment, after) = parse_comment(comment)?;
+                let last = self.moves.last_mut().ok_or(String::from("Comment before the first move"))?;
+                let mut comment_text = comment.trim();
+                if let Some(eval) = comment_text.strip_prefix("[%eval ") {
+                    let close = eval.find(']').ok_or(String::from("Unclosed evaluation"))?;
+                    last.eval = Some(eval[..close].trim().parse::<i16>().map_err(|e| format!("Invalid evaluation: {}", e))?);
+                    comment_text = eval[close + 1..].trim();
+                }
+                if !comment_text.is_empty() {
+                    last.comment = Some(comment_text.to_string());
+                }
+                rest = after;
+                continue;
+            }
+            let end = rest.find(|c: char| c.is_whitespace() || c == '{').unwrap_or(rest.len());
+            let token = &rest[..end];
+            rest = &rest[end..];
+            if is_result_token(token) {
+                if self.header("Result").is_none() {
+                    self.set_header("Result", token);
+                }
+                continue;
+            }
+            if token.ends_with('.') && token[..token.len() - 1].chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let termination = match token.chars().next() {
+                Some('R') => Some(Termination::Resignation),
+                Some('T') => Some(Termination::TimeForfeit),
+                _ => None
+            };
+            if let Some(termination) = termination {
+                let player_index = token[1..]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|player| player.checked_sub(1))
+                    .ok_or(format!("Invalid elimination \"{}\"", token))?;
+                self.eliminations.push(Elimination { player_index, ply: self.moves.len(), termination });
+                continue;
+            }
+            let played = Move::parse(token).map_err(|_| format!("Invalid move \"{}\"", token))?;
+            self.moves.push(RecordedMove::new(played));
+        }
+    }
+
+    /// Appends the record to `path`, creating the file if needed.
+    pub fn append_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let needs_separator = fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        let separator = if needs_separator { "\n" } else { "" };
+        write!(file, "{}{}", separator, self).map_err(|e| e.to_string())
+    }
+
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        GameRecord::parse_all(&text)
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.headers {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        let players = (1..).take_while(|n| self.header(&format!("Player{}", n)).is_some()).count().max(2);
+        let eliminations = |ply| self.eliminations
+            .iter()
+            .filter(move |elimination| elimination.ply == ply)
+            .map(|elimination| match elimination.termination {
+                Termination::TimeForfeit => format!("T{}", elimination.player_index + 1),
+                _ => format!("R{}", elimination.player_index + 1)
+            });
+        let mut tokens = vec![];
+        for (ply, recorded) in self.moves.iter().enumerate() {
+            tokens.extend(eliminations(ply));
+            if ply % players == 0 {
+                tokens.push(format!("{}.", ply / players + 1));
+            }
+            tokens.push(recorded.played.to_string());
+            let comment = match (recorded.eval, &recorded.comment) {
+                (Some(eval), Some(comment)) => Some(format!("[%eval {}] {}", eval, comment)),
+                (Some(eval), None) => Some(format!("[%eval {}]", eval)),
+                (None, Some(comment)) => Some(comment.clone()),
+                (None, None) => None
+            };
+            if let Some(comment) = comment {
+                tokens.push(format!("{{{}}}", comment.replace('\\', "\\\\").replace('}', "\\}")));
+            }
+        }
+        tokens.extend(eliminations(self.moves.len()));
+        tokens.push(self.header("Result").unwrap_or("*").to_string());
+        writeln!(f, "{}", tokens.join(" "))
+    }
+}
+
+
+/// Appends every finished game to a file.
+#[derive(Debug, Clone)]
+pub struct RecordObserver {
+    path: PathBuf
+}
+
+impl RecordObserver {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl GameObserver for RecordObserver {
+    fn notify(&mut self, game: &Game, event: &GameEvent) {
+        if matches!(event, GameEvent::Won { .. } | GameEvent::Draw | GameEvent::Aborted { .. })
+            && let Err(e) = GameRecord::from_game(game).append_to_file(&self.path) {
+            eprintln!("Could not save the game to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod record_tests {
+    use super::*;
+
+    fn finished_game() -> Game {
+        let mut game = Game::new(Board::new(), Game::generate_players(), Game::setup_win_condition_strategies());
+        for col in [3, 3, 4, 4, 5, 5, 6] {
+            game.play_move(col).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn record_round_trips() {
+        let mut record = GameRecord::from_game(&finished_game());
+        record.moves[0].eval = Some(12);
+        record.moves[0].comment = Some(String::from("the centre"));
+        record.moves[2].comment = Some(String::from("a {braced} \"comment\" \\o/"));
+        record.set_header("Event", "Club \"night\"");
+        let text = record.to_string();
+        assert!(text.contains("\n1. 3 {[%eval 12] the centre} 3 2. 4 {a {braced\\} \"comment\" \\\\o/} 4 3. 5 5 4. 6 1-0\n"));
+
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed.header("Event"), Some("Club \"night\""));
+        assert_eq!(parsed.header("Player2Type"), Some("ai 8"));
+        assert_eq!(parsed.moves_string(), "3344556");
+        assert_eq!(parsed.moves[0], RecordedMove { played: Move::Drop(3), eval: Some(12), comment: Some(String::from("the centre")) });
+        assert_eq!(parsed.moves[2].comment, record.moves[2].comment);
+
+        let game = parsed.to_game().unwrap();
+        assert_eq!(game.status(), GameStatus::Won(game.players()[0].clone()));
+        assert_eq!(game.players()[1].player_type, PlayerType::AI(8));
+    }
+
+    #[test]
+    fn files_hold_several_games() {
+        let path = std::env::temp_dir().join(format!("connect_four_records_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut game = finished_game();
+        game.add_observer(RecordObserver::new(&path));
+        game.restart();
+        for col in [0, 1, 0, 1, 0, 1, 0] {
+            game.play_move(col).unwrap();
+        }
+        GameRecord::from_game(&Game::create_simulated_game()).append_to_file(&path).unwrap();
+
+        let mut flagged = Game::create_simulated_game();
+        flagged.set_first_player(1).unwrap();
+        flagged.set_time_control(Some(TimeControl::parse("0+5").unwrap()));
+        assert!(flagged.play_move(3).is_err());
+        GameRecord::from_game(&flagged).append_to_file(&path).unwrap();
+
+        let records = GameRecord::read_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].moves_string(), "0101010");
+        assert_eq!(records[0].header("Result"), Some("1-0"));
+        assert_eq!(records[1].header("Result"), Some("*"));
+        assert_eq!(records[1].header("Termination"), Some("unterminated"));
+        assert_eq!(records[2].header("TimeControl"), Some("0+5"));
+        assert_eq!(records[2].header("Termination"), Some("time forfeit"));
+        let replayed = records[2].to_game().unwrap();
+        assert_eq!(replayed.status(), GameStatus::Won(replayed.players()[0].clone()));
+        assert_eq!(records[2].header("FirstPlayer"), Some("2"));
+        assert_eq!(replayed.time_control(), Some(TimeControl::parse("0+5").unwrap()));
+    }
+
+    #[test]
+    fn early_endings_round_trip() {
+        let mut resigned = Game::create_simulated_game();
+        resigned.play_move(3).unwrap();
+        resigned.resign(1).unwrap();
+        let mut agreed = Game::create_simulated_game();
+        agreed.offer_draw(0).unwrap();
+        agreed.play_move(3).unwrap();
+        agreed.offer_draw(1).unwrap();
+        let mut aborted = Game::create_simulated_game();
+        aborted.abort(0).unwrap();
+
+        for (game, result, termination) in [
+            (resigned, "1-0", "resignation"),
+            (agreed, "1/2-1/2", "agreement"),
+            (aborted, "*", "abandoned")
+        ] {
+            let record = GameRecord::parse(&GameRecord::from_game(&game).to_string()).unwrap();
+            assert_eq!((record.header("Result"), record.header("Termination")), (Some(result), Some(termination)));
+            let replayed = record.to_game().unwrap();
+            assert_eq!((replayed.status(), replayed.termination()), (game.status(), game.termination()));
+        }
+    }
+
+    #[test]
+    fn multiplayer_games_round_trip() {
+        let players = Game::generate_simulation_players_for(3).unwrap();
+        let mut game = Game::new(Board::with_size(7, 9, 5).unwrap(), players.clone(), Game::setup_win_condition_strategies());
+        game.play_move(4).unwrap();
+        game.resign(1).unwrap();
+        game.resign(2).unwrap();
+        let record = GameRecord::parse(&GameRecord::from_game(&game).to_string()).unwrap();
+        assert_eq!(record.header("Result"), Some("1-0-0"));
+        assert_eq!(record.header("WinLength"), Some("5"));
+        let replayed = record.to_game().unwrap();
+        assert_eq!((replayed.status(), replayed.board().get_cols()), (GameStatus::Won(players[0].clone()), 9));
+        assert!(is_result_token("1/3-1/3-1/3") && !is_result_token("1-1-0"));
+    }
+
+    #[test]
+    fn draws_agreed_after_a_resignation_round_trip() {
+        let players = Game::generate_simulation_players_for(3).unwrap();
+        let mut game = Game::new(Board::for_players(3), players, Game::setup_win_condition_strategies());
+        game.play_move(4).unwrap();
+        game.resign(1).unwrap();
+        game.offer_draw(2).unwrap();
+        game.offer_draw(0).unwrap();
+        assert_eq!(game.status(), GameStatus::Draw);
+
+        let record = GameRecord::parse(&GameRecord::from_game(&game).to_string()).unwrap();
+        assert_eq!(record.header("Termination"), Some("agreement"));
+        let replayed = record.to_game().unwrap();
+        assert_eq!((replayed.status(), replayed.termination()), (GameStatus::Draw, Some(Termination::Agreement)));
+        assert!(replayed.is_eliminated(1));
+    }
+
+    #[test]
+    fn variants_round_trip() {
+        let mut board = Board::new();
+        board.set_variant(Variant::PopOut);
+        board.set_topology(Topology::Cylinder);
+        board.set_blocked(5, 0).unwrap();
+        let mut game = Game::new(board, Game::generate_simulation_players(), Game::setup_win_condition_strategies());
+        for mv in [Move::Drop(3), Move::Drop(4), Move::Pop(3)] {
+            game.make_move(mv).unwrap();
+        }
+        let text = GameRecord::from_game(&game).to_string();
+        assert!(text.contains("[Variant \"popout\"]") && text.contains("[Topology \"cylinder\"]"));
+        assert!(text.contains("[Blockers \"5:0\"]"));
+        assert!(text.contains("1. 3 4 2. p3 *"));
+        let replayed = GameRecord::parse(&text).unwrap().to_game().unwrap();
+        assert_eq!(replayed.moves(), game.moves());
+        assert_eq!((replayed.board().topology(), replayed.board().blocked_cells()), (Topology::Cylinder, vec![(5, 0)]));
+        assert_eq!(replayed.board().cells(), game.board().cells());
+    }
+
+    #[test]
+    fn eliminations_keep_the_turn_order() {
+        let players = Game::generate_simulation_players_for(3).unwrap();
+        let mut game = Game::new(Board::for_players(3), players, Game::setup_win_condition_strategies());
+        game.play_move(0).unwrap();
+        game.resign(1).unwrap();
+        for col in [8, 1, 8, 2, 8, 3] {
+            game.play_move(col).unwrap();
+        }
+        assert_eq!(game.status(), GameStatus::Won(game.players()[0].clone()));
+
+        let text = GameRecord::from_game(&game).to_string();
+        assert!(text.contains("1. 0 R2 8 1 2. 8 2 8 3. 3 1-0-0\n"));
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed.eliminations, game.eliminations());
+        let replayed = parsed.to_game().unwrap();
+        assert_eq!(replayed.status(), GameStatus::Won(replayed.players()[0].clone()));
+        assert_eq!(replayed.movers(), [0, 2, 0, 2, 0, 2, 0]);
+        assert!(replayed.is_eliminated(1));
+        assert!(GameRecord::parse("[Player1 \"a\"]\n[Player2 \"b\"]\n\n1. 3 R0 *").is_err());
+    }
+
+    #[test]
+    fn invalid_records_are_rejected() {
+        assert!(GameRecord::parse("[Player1 \"a\"]\n[Player2 \"b\"]\n\n1. 3 {unclosed").is_err());
+        assert!(GameRecord::parse("[Player1 \"a\"]\n[Player2 \"b\"]\n\n1. x").is_err());
+        let full_column = GameRecord::parse("[Player1 \"a\"]\n[Player2 \"b\"]\n\n1. 0 0 2. 0 0 3. 0 0 4. 0 *").unwrap();
+        assert_eq!(full_column.to_game().err(), Some(String::from("Move 7: Invalid column 0")));
+        let huge_board = GameRecord::parse("[Player1 \"a\"]\n[Player2 \"b\"]\n[Rows \"11\"]\n\n*").unwrap();
+        assert!(huge_board.to_game().is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::IsTerminal;
+
+use crate::board::{Board, Cell};
+use crate::player::Player;
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const REVERSE: &str = "\x1b[7m";
+const PLAYER_COLORS: [&str; 4] = ["\x1b[91m", "\x1b[93m", "\x1b[92m", "\x1b[94m"];
+const DISC: char = '●';
+const LAST_MOVE_DISC: char = '◉';
+const EMPTY: char = '·';
+const BLOCKED: char = '■';
+
+/// Renders boards with ANSI colours and Unicode discs, or as the plain `Display` output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Renderer {
+    pub rich: bool
+}
+
+
+impl Renderer {
+    pub fn plain() -> Self {
+        Self { rich: false }
+    }
+
+    pub fn rich() -> Self {
+        Self { rich: true }
+    }
+
+    /// Rich output only when stdout is a terminal and `NO_COLOR` is not set.
+    pub fn detect() -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self { rich: !no_color && std::io::stdout().is_terminal() }
+    }
+
+    pub fn player_color(player: &Player) -> &'static str {
+        PLAYER_COLORS[(player.id as usize + PLAYER_COLORS.len() - 1) % PLAYER_COLORS.len()]
+    }
+
+    /// Board as text, `last_move` and `winning_line` are (row, col) cells to highlight.
+    pub fn render(&self, board: &Board, last_move: Option<(u8, u8)>, winning_line: &[(u8, u8)]) -> String {
+        if !self.rich {
+            return board.to_string();
+        }
+        let mut out = String::from(" ");
+        for col in 0..board.get_cols() {
+            out.push_str(&format!(" {} ", col));
+        }
+        out.push_str(&format!("\n{}╭{}╮{}\n", DIM, "─".repeat(board.get_cols() as usize * 3), RESET));
+
+        for row in 0..board.get_rows() {
+            out.push_str(&format!("{}│{}", DIM, RESET));
+            for col in 0..board.get_cols() {
+                let cell = (row, col);
+                let player = match board.cell(row, col) {
+                    Cell::Empty => {
+                        out.push_str(&format!(" {}{}{} ", DIM, EMPTY, RESET));
+                        continue;
+                    },
+                    Cell::Blocked => {
+                        out.push_str(&format!(" {}{}{} ", DIM, BLOCKED, RESET));
+                        continue;
+                    },
+                    Cell::Disc(player) => player
+                };
+                let glyph = if last_move == Some(cell) { LAST_MOVE_DISC } else { DISC };
+                let highlight = if winning_line.contains(&cell) { REVERSE } else { "" };
+                out.push_str(&format!(" {}{}{}{} ", Renderer::player_color(player), highlight, glyph, RESET));
+            }
+            out.push_str(&format!("{}│{}\n", DIM, RESET));
+        }
+        out.push_str(&format!("{}╰{}╯{}\n", DIM, "─".repeat(board.get_cols() as usize * 3), RESET));
+        out
+    }
+}
+
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn plain_renderer_matches_display() {
+        let b = Board::new();
+        assert_eq!(Renderer::plain().render(&b, None, &[]), b.to_string());
+    }
+
+    #[test]
+    fn rich_renderer_colors_and_highlights() {
+        let players = Game::generate_simulation_players();
+        let mut b = Board::new();
+        b.play_moves("0101010", &players).unwrap();
+        b.set_blocked(5, 6).unwrap();
+        let winning_line = [(5, 0), (4, 0), (3, 0), (2, 0)];
+        let out = Renderer::rich().render(&b, Some((2, 0)), &winning_line);
+
+        assert!(out.contains(&format!("{}{}{}", Renderer::player_color(&players[0]), REVERSE, LAST_MOVE_DISC)));
+        assert!(out.contains(&format!("{}{}", Renderer::player_color(&players[1]), DISC)));
+        assert_eq!(out.matches(REVERSE).count(), 4);
+        assert_eq!(out.matches(BLOCKED).count(), 1);
+        assert!(b.to_string().contains(" # |"));
+        assert_eq!(out.lines().count(), b.get_rows() as usize + 3);
+    }
+}
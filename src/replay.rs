@@ -0,0 +1,288 @@
+use rayon::prelude::*;
+
+use crate::board::Board;
+use crate::engine::{is_decisive, Engine};
+use crate::game::Game;
+use crate::player::Player;
+use crate::record::GameRecord;
+use crate::utils::get_cli_input;
+use crate::variant::Move;
+use crate::win_condition_strategy::decide_winner;
+
+/// A finished or unfinished two player game that can be stepped through ply by ply.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    players: Vec<Player>,
+    moves: Vec<Move>,
+    movers: Vec<usize>,
+    positions: Vec<Board>,
+    evaluations: Vec<i16>,
+    best_moves: Vec<Option<Move>>,
+    decisive_ply: Option<usize>,
+    ply: usize
+}
+
+
+impl Replay {
+    /// Plays `moves` by `players[movers[ply]]` on the empty `start` board and evaluates every position with an engine
+    /// searching `depth` plies. Scores only compare two players, so games with more players are refused.
+    pub fn new(start: Board, players: Vec<Player>, moves: Vec<Move>, movers: Vec<usize>, depth: u8) -> Result<Self, String> {
+        if players.len() != 2 {
+            return Err(format!("Only two player games can be replayed, this one has {} players", players.len()));
+        }
+        if movers.len() != moves.len() || movers.iter().any(|mover| *mover >= players.len()) {
+            return Err(String::from("Every move needs one of the players as its mover"));
+        }
+        let strategies = Game::setup_win_condition_strategies();
+        let mut board = start;
+        let mut positions = vec![board.clone()];
+        for (ply, mv) in moves.iter().enumerate() {
+            let previous = ply.checked_sub(1).map_or(0, |previous| movers[previous]);
+            if decide_winner(&strategies, &board, &players, previous).is_some() {
+                return Err(format!("Move {} is played after the game was won", ply + 1));
+            }
+            board
+                .play(*mv, &players[movers[ply]])
+                .ok_or(format!("Move {}: invalid move {}", ply + 1, mv))?;
+            positions.push(board.clone());
+        }
+
+        // scores are from the first seat's point of view
+        let to_move = |ply: usize| movers
+            .get(ply)
+            .copied()
+            .unwrap_or_else(|| movers.last().map_or(0, |last| (last + 1) % players.len()));
+        let (evaluations, best_moves): (Vec<i16>, Vec<Option<Move>>) = positions
+            .par_iter()
+            .enumerate()
+            .map(|(ply, position)| {
+                let to_move = to_move(ply);
+                let result = Engine::new(depth).search_position(position, &players, to_move);
+                (if to_move == 0 { result.score } else { -result.score }, result.best_move)
+            })
+            .unzip();
+
+        let last_mover = movers.last().copied().unwrap_or(0);
+        let final_winner = decide_winner(&strategies, &board, &players, last_mover);
+        let decisive_ply = final_winner.and_then(|winner| {
+            let winning = |score: i16| is_decisive(score) && (score > 0) == (winner == 0);
+            let decided_from = evaluations
+                .iter()
+                .rposition(|score| !winning(*score))
+                .map_or(0, |position| position + 1);
+            decided_from.checked_sub(1)
+        });
+
+        Ok(Self { players, moves, movers, positions, evaluations, best_moves, decisive_ply, ply: 0 })
+    }
+
+    /// Replay of a move string like `"3344"`, played by two computer players.
+    pub fn from_moves(moves: &str, depth: u8) -> Result<Self, String> {
+        let moves = moves
+            .trim()
+            .chars()
+            .map(|c| c.to_digit(10).map(|col| Move::Drop(col as u8)).ok_or(format!("Invalid move '{}' in \"{}\"", c, moves)))
+            .collect::<Result<Vec<Move>, String>>()?;
+        let movers = (0..moves.len()).map(|ply| ply % 2).collect();
+        Replay::new(Board::new(), Game::generate_simulation_players(), moves, movers, depth)
+    }
+
+    /// Replay of a recorded game, its turn order is taken from the game played out.
+    pub fn from_record(record: &GameRecord, depth: u8) -> Result<Self, String> {
+        let game = record.to_game()?;
+        Replay::new(record.board()?, game.players().to_vec(), game.moves().to_vec(), game.movers().to_vec(), depth)
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Index of the player behind every move.
+    pub fn movers(&self) -> &[usize] {
+        &self.movers
+    }
+
+    /// Boards after 0, 1, .. moves.
+    pub fn positions(&self) -> &[Board] {
+        &self.positions
+    }
+
+    /// Engine score of every position, positive when it favours the first seat.
+    pub fn evaluations(&self) -> &[i16] {
+        &self.evaluations
+    }
+
+    /// Engine move for the side to move in every position, `None` once the game is over.
+    pub fn best_moves(&self) -> &[Option<Move>] {
+        &self.best_moves
+    }
+
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.positions[self.ply]
+    }
+
+    /// Engine score of the current position, positive when it favours the first seat.
+    pub fn evaluation(&self) -> i16 {
+        self.evaluations[self.ply]
+    }
+
+    /// Index of the move after which the engine saw the final result as forced.
+    pub fn decisive_ply(&self) -> Option<usize> {
+        self.decisive_ply
+    }
+
+    pub fn forward(&mut self) -> bool {
+        self.jump(self.ply + 1).is_ok()
+    }
+
+    pub fn back(&mut self) -> bool {
+        self.ply.checked_sub(1).is_some_and(|ply| self.jump(ply).is_ok())
+    }
+
+    /// Shows the position after `ply` moves.
+    pub fn jump(&mut self, ply: usize) -> Result<(), String> {
+        if ply > self.len() {
+            return Err(format!("The game has only {} moves", self.len()));
+        }
+        self.ply = ply;
+        Ok(())
+    }
+
+    /// The current board, the move leading to it and the evaluation.
+    pub fn view(&self) -> String {
+        let mut out = format!("{}\n", self.board());
+        if self.ply == 0 {
+            out.push_str(&format!("Start position (0/{})\n", self.len()));
+        } else {
+            let mover = &self.players[self.movers[self.ply - 1]];
+            let played = match self.moves[self.ply - 1] {
+                Move::Drop(col) => format!("played column {}", col),
+                Move::Pop(col) => format!("popped column {}", col),
+                Move::Capture(col) => format!("captured column {}", col),
+                Move::Return(from, to) => format!("popped column {} into column {}", from, to)
+            };
+            out.push_str(&format!("Move {}/{}: {} {}\n", self.ply, self.len(), mover, played));
+        }
+        out.push_str(&format!("Evaluation: {:+} for {}\n", self.evaluation(), self.players[0]));
+        match self.best_moves[self.ply] {
+            Some(Move::Drop(col)) => out.push_str(&format!("Best move: column {}\n", col)),
+            Some(Move::Pop(col)) => out.push_str(&format!("Best move: pop column {}\n", col)),
+            Some(Move::Capture(col)) => out.push_str(&format!("Best move: capture column {}\n", col)),
+            Some(Move::Return(from, to)) => out.push_str(&format!("Best move: pop column {} into column {}\n", from, to)),
+            None => {}
+        }
+        if let Some(decisive) = self.decisive_ply {
+            let mover = &self.players[self.movers[decisive]];
+            let winner = if self.evaluations[self.len()] > 0 { &self.players[0] } else { &self.players[1] };
+            let kind = if mover == winner { "winning move" } else { "blunder" };
+            if decisive + 1 == self.ply {
+                out.push_str(&format!(">>> The result was decided here, {} by {}\n", kind, mover));
+            } else {
+                out.push_str(&format!("The result was decided by move {} ({} by {})\n", decisive + 1, kind, mover));
+            }
+        }
+        out
+    }
+}
+
+
+/// Steps through `replay` with commands read from the command line.
+pub fn run_replay(mut replay: Replay) -> Result<(), String> {
+    loop {
+        println!("{}", replay.view());
+        print!("[n]ext, [p]revious, [g]o <move>, [s]tart, [e]nd, [q]uit: ");
+        let input = get_cli_input();
+        let mut fields = input.split_whitespace();
+        match fields.next().unwrap_or("n") {
+            "n" => if !replay.forward() { println!("Already at the last move") },
+            "p" => if !replay.back() { println!("Already at the start") },
+            "g" => {
+                let ply = fields.next().and_then(|ply| ply.parse::<usize>().ok());
+                match ply.map(|ply| replay.jump(ply)) {
+                    Some(Ok(())) => {},
+                    Some(Err(e)) => println!("{}", e),
+                    None => println!("Usage: g <move>")
+                }
+            },
+            "s" => replay.ply = 0,
+            "e" => replay.ply = replay.len(),
+            "q" => return Ok(()),
+            other => println!("Unknown command \"{}\"", other)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+
+    #[test]
+    fn steps_through_the_game() {
+        let mut replay = Replay::from_moves("3344556", 4).unwrap();
+        assert_eq!(replay.len(), 7);
+        assert!(!replay.back());
+        assert!(replay.forward());
+        assert_eq!(replay.board().disc_count(), 1);
+        replay.jump(7).unwrap();
+        assert!(!replay.forward());
+        assert!(replay.jump(8).is_err());
+        assert!(replay.view().contains("Move 7/7: x played column 6"));
+        assert!(replay.evaluation() > 0 && is_decisive(replay.evaluation()));
+    }
+
+    #[test]
+    fn finds_the_deciding_move() {
+        // o answers x's second bottom row disc on top of it, x then gets an open three
+        let mut replay = Replay::from_moves("3344556", 4).unwrap();
+        assert_eq!(replay.decisive_ply(), Some(3));
+        replay.jump(4).unwrap();
+        assert!(replay.view().contains(">>> The result was decided here, blunder by o"));
+        replay.jump(1).unwrap();
+        assert!(replay.view().contains("The result was decided by move 4 (blunder by o)"));
+
+        let unfinished = Replay::from_moves("33", 4).unwrap();
+        assert_eq!(unfinished.decisive_ply(), None);
+    }
+
+    #[test]
+    fn follows_the_recorded_movers() {
+        let mut game = Game::create_simulated_game();
+        game.set_first_player(1).unwrap();
+        for col in [3, 3, 4, 4, 5, 5, 6] {
+            game.play_move(col).unwrap();
+        }
+        let record = GameRecord::from_game(&game);
+        let mut replay = Replay::from_record(&record, 4).unwrap();
+        assert_eq!(replay.movers(), [1, 0, 1, 0, 1, 0, 1]);
+        replay.jump(7).unwrap();
+        assert!(replay.view().contains("Move 7/7: o played column 6"));
+        assert!(replay.evaluation() < 0 && is_decisive(replay.evaluation()));
+
+        let three_players = Game::new(Board::for_players(3), Game::generate_simulation_players_for(3).unwrap(), vec![]);
+        assert!(Replay::from_record(&GameRecord::from_game(&three_players), 2).is_err());
+    }
+
+    #[test]
+    fn rejects_illegal_games() {
+        assert!(Replay::from_moves("0000000", 2).is_err());
+        assert!(Replay::from_moves("01010101", 2).is_err());
+        assert!(Replay::from_moves("3a", 2).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use rand::Rng;
+
+use crate::game::{Game, GameStatus};
+use crate::player::Player;
+
+/// When a match is over.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MatchLength {
+    BestOf(u32), // until a player has more than half of the points
+    Games(u32)
+}
+
+/// Who moves first in each game of a match.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StartOrder {
+    Alternate,
+    Random
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MatchGame {
+    pub first_player: Player,
+    pub status: GameStatus
+}
+
+/// A series of games between the same players with a running score.
+#[derive(Debug, Clone)]
+pub struct MatchSeries {
+    length: MatchLength,
+    start_order: StartOrder,
+    players: Vec<Player>,
+    half_points: Vec<u32>,
+    games: Vec<MatchGame>
+}
+
+
+/// Points with a `½` for draws.
+fn format_points(half_points: u32) -> String {
+    match (half_points / 2, half_points % 2) {
+        (0, 1) => String::from("½"),
+        (points, 1) => format!("{}½", points),
+        (points, _) => points.to_string()
+    }
+}
+
+
+impl MatchSeries {
+    pub fn new(length: MatchLength, start_order: StartOrder, players: &[Player]) -> Self {
+        Self {
+            length,
+            start_order,
+            players: players.to_vec(),
+            half_points: vec![0; players.len()],
+            games: vec![]
+        }
+    }
+
+    pub fn games(&self) -> &[MatchGame] {
+        &self.games
+    }
+
+    /// Points of `players[index]`, a win counts 1 and a draw ½.
+    pub fn points(&self, index: usize) -> f32 {
+        self.half_points[index] as f32 / 2.0
+    }
+
+    pub fn is_over(&self) -> bool {
+        match self.length {
+            MatchLength::Games(games) => self.games.len() as u32 >= games,
+            MatchLength::BestOf(games) => {
+                self.games.len() as u32 >= games || self.half_points.iter().any(|half_points| *half_points > games)
+            }
+        }
+    }
+
+    /// Index of the player moving first in the next game.
+    pub fn next_first_player(&self) -> usize {
+        match self.start_order {
+            StartOrder::Alternate => self.games.len() % self.players.len(),
+            StartOrder::Random => rand::thread_rng().gen_range(0..self.players.len())
+        }
+    }
+
+    pub fn add_result(&mut self, first_player: usize, status: GameStatus) {
+        match &status {
+            GameStatus::Won(winner) => {
+                if let Some(index) = self.players.iter().position(|player| player == winner) {
+                    self.half_points[index] += 2;
+                }
+            },
+            GameStatus::Draw => self.half_points.iter_mut().for_each(|half_points| *half_points += 1),
+            GameStatus::InProgress | GameStatus::Aborted => return
+        }
+        self.games.push(MatchGame { first_player: self.players[first_player].clone(), status });
+    }
+
+    /// Player with the most points once the match is over, `None` for a tie.
+    pub fn winner(&self) -> Option<Player> {
+        let best = *self.half_points.iter().max()?;
+        let mut leaders = self.players.iter().zip(&self.half_points).filter(|(_, points)| **points == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((leader, _)), None) if self.is_over() => Some(leader.clone()),
+            _ => None
+        }
+    }
+
+    /// Every game with who started and the result, then the final score.
+    pub fn summary(&self) -> String {
+        let mut out = String::from("Match summary:\n");
+        for (number, game) in self.games.iter().enumerate() {
+            let result = match &game.status {
+                GameStatus::Won(winner) => format!("{} won", winner.name),
+                GameStatus::Draw => String::from("draw"),
+                GameStatus::InProgress | GameStatus::Aborted => String::from("unfinished")
+            };
+            out.push_str(&format!("Game {}: {} started, {}\n", number + 1, game.first_player.name, result));
+        }
+        out.push_str(&format!("Final score: {}\n", self));
+        match self.winner() {
+            Some(winner) => out.push_str(&format!("{} wins the match!\n", winner.name)),
+            None if self.is_over() => out.push_str("The match is tied.\n"),
+            None => out.push_str("The match was not finished.\n")
+        }
+        out
+    }
+
+    /// Plays games on `game` until the match is over or a game is aborted, printing the score after every game.
+    pub fn run(&mut self, game: &mut Game) -> Result<(), String> {
+        while !self.is_over() {
+            let first_player = self.next_first_player();
+            game.restart();
+            game.set_first_player(first_player)?;
+            println!("Game {}, {} starts", self.games.len() + 1, self.players[first_player].name);
+            let status = game.play_game()?;
+            if status == GameStatus::Aborted {
+                println!("Match aborted");
+                break;
+            }
+            self.add_result(first_player, status);
+            println!("Score: {}", self);
+        }
+        print!("{}", self.summary());
+        Ok(())
+    }
+}
+
+impl fmt::Display for MatchSeries {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scores: Vec<String> = self.players
+            .iter()
+            .zip(&self.half_points)
+            .map(|(player, half_points)| format!("{} {}", player.name, format_points(*half_points)))
+            .collect();
+        write!(f, "{}", scores.join(" - "))
+    }
+}
+
+
+#[cfg(test)]
+mod series_tests {
+    use super::*;
+
+    #[test]
+    fn best_of_ends_once_decided() {
+        let players = Game::generate_simulation_players();
+        let mut series = MatchSeries::new(MatchLength::BestOf(3), StartOrder::Alternate, &players);
+        assert_eq!(series.next_first_player(), 0);
+        series.add_result(0, GameStatus::Won(players[0].clone()));
+        assert_eq!(series.next_first_player(), 1);
+        series.add_result(1, GameStatus::Draw);
+        assert!(!series.is_over());
+        assert_eq!(series.to_string(), "henk-one 1½ - henk-two ½");
+        series.add_result(0, GameStatus::Won(players[0].clone()));
+        assert!(series.is_over());
+        assert_eq!(series.winner(), Some(players[0].clone()));
+        assert!(series.summary().contains("Game 2: henk-two started, draw\n"));
+    }
+
+    #[test]
+    fn fixed_length_match_alternates_and_can_tie() {
+        let players = Game::generate_simulation_players();
+        let mut game = Game::create_simulated_game();
+        let events = game.subscribe();
+        let mut series = MatchSeries::new(MatchLength::Games(2), StartOrder::Alternate, &players);
+        series.run(&mut game).unwrap();
+
+        assert_eq!(series.games().len(), 2);
+        assert_eq!(series.games()[1].first_player, players[1]);
+        assert_eq!(series.points(0) + series.points(1), 2.0);
+        assert!(events.try_iter().any(|event| matches!(event, crate::events::GameEvent::MovePlayed { .. })));
+
+        let mut tied = MatchSeries::new(MatchLength::Games(2), StartOrder::Random, &players);
+        tied.add_result(tied.next_first_player(), GameStatus::Won(players[0].clone()));
+        tied.add_result(tied.next_first_player(), GameStatus::Won(players[1].clone()));
+        assert_eq!(tied.winner(), None);
+        assert!(tied.summary().ends_with("The match is tied.\n"));
+    }
+}
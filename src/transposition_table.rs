@@ -0,0 +1,264 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::variant::Move;
+
+const OCCUPIED_BIT: u64 = 1 << 63;
+const NO_MOVE: u64 = 0xFF;
+const POP_BIT: u64 = 0x80;
+const CAPTURE_BIT: u64 = 0x40;
+const RETURN_BIT: u64 = 0x100; // with the columns popped from and dropped into in two nibbles
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReplacementPolicy {
+    AlwaysReplace,
+    DepthPreferred
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TableEntry {
+    pub score: i16,
+    pub depth: u8,
+    pub bound: Bound,
+    pub best_move: Option<Move>
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct TableStats {
+    pub probes: u64,
+    pub hits: u64,
+    pub stores: u64,
+    pub overwrites: u64,
+    pub rejected: u64
+}
+
+/// A slot stores `key ^ data` next to `data`, so a torn write from another thread
+/// simply fails the key check on probe instead of returning a corrupt entry.
+#[derive(Debug, Default)]
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64
+}
+
+/// Fixed-size hash table keyed by `Board::hash`, shareable between threads without locks.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: u64,
+    policy: ReplacementPolicy,
+    probes: AtomicU64,
+    hits: AtomicU64,
+    stores: AtomicU64,
+    overwrites: AtomicU64,
+    rejected: AtomicU64
+}
+
+
+impl TableEntry {
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2
+        };
+        let best_move = match self.best_move {
+            None => NO_MOVE,
+            Some(Move::Drop(col)) => col as u64,
+            Some(Move::Pop(col)) => POP_BIT | col as u64,
+            Some(Move::Capture(col)) => CAPTURE_BIT | col as u64,
+            Some(Move::Return(from, to)) => RETURN_BIT | (from as u64) << 4 | to as u64
+        };
+        OCCUPIED_BIT
+            | (self.score as u16 as u64)
+            | (self.depth as u64) << 16
+            | bound << 24
+            | best_move << 32
+    }
+
+    fn unpack(data: u64) -> Option<Self> {
+        if data & OCCUPIED_BIT == 0 {
+            return None;
+        }
+        let bound = match (data >> 24) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper
+        };
+        let best_move = (data >> 32) & 0x1FF;
+        Some(Self {
+            score: data as u16 as i16,
+            depth: (data >> 16) as u8,
+            bound,
+            best_move: match best_move {
+                NO_MOVE => None,
+                back if back & RETURN_BIT != 0 => Some(Move::Return((back >> 4 & 0xF) as u8, (back & 0xF) as u8)),
+                pop if pop & POP_BIT != 0 => Some(Move::Pop((pop & !POP_BIT) as u8)),
+                capture if capture & CAPTURE_BIT != 0 => Some(Move::Capture((capture & !CAPTURE_BIT) as u8)),
+                col => Some(Move::Drop(col as u8))
+            }
+        })
+    }
+}
+
+
+impl TranspositionTable {
+    /// Creates a table with at least `capacity` slots, rounded up to a power of two.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_policy(capacity, ReplacementPolicy::DepthPreferred)
+    }
+
+    pub fn with_policy(capacity: usize, policy: ReplacementPolicy) -> Self {
+        let capacity = capacity.max(1).next_power_of_two();
+        Self {
+            slots: (0..capacity).map(|_| Slot::default()).collect(),
+            mask: capacity as u64 - 1,
+            policy,
+            probes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            stores: AtomicU64::new(0),
+            overwrites: AtomicU64::new(0),
+            rejected: AtomicU64::new(0)
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn policy(&self) -> ReplacementPolicy {
+        self.policy
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[(key & self.mask) as usize]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        self.probes.fetch_add(1, Ordering::Relaxed);
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+        if slot.check.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        let entry = TableEntry::unpack(data)?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(entry)
+    }
+
+    pub fn store(&self, key: u64, entry: TableEntry) {
+        let slot = self.slot(key);
+        let old_data = slot.data.load(Ordering::Relaxed);
+        if let Some(old_entry) = TableEntry::unpack(old_data) {
+            let same_position = slot.check.load(Ordering::Relaxed) ^ old_data == key;
+            if !same_position
+                && self.policy == ReplacementPolicy::DepthPreferred
+                && old_entry.depth > entry.depth {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            if !same_position {
+                self.overwrites.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let data = entry.pack();
+        slot.check.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+        self.stores.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of occupied slots.
+    pub fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.data.load(Ordering::Relaxed) & OCCUPIED_BIT != 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> TableStats {
+        TableStats {
+            probes: self.probes.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            stores: self.stores.load(Ordering::Relaxed),
+            overwrites: self.overwrites.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed)
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        for counter in [&self.probes, &self.hits, &self.stores, &self.overwrites, &self.rejected] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod transposition_table_tests {
+    use super::*;
+
+    fn entry(score: i16, depth: u8) -> TableEntry {
+        TableEntry { score, depth, bound: Bound::Exact, best_move: Some(Move::Drop(3)) }
+    }
+
+    #[test]
+    fn stored_entry_can_be_probed() {
+        let tt = TranspositionTable::new(1000);
+        assert_eq!(tt.capacity(), 1024);
+
+        let stored = TableEntry { score: -17, depth: 9, bound: Bound::Upper, best_move: None };
+        tt.store(42, stored);
+        assert_eq!(tt.probe(42), Some(stored));
+        assert_eq!(tt.probe(43), None);
+        assert_eq!(tt.len(), 1);
+
+        let stats = tt.stats();
+        assert_eq!((stats.probes, stats.hits, stats.stores), (2, 1, 1));
+
+        for (key, best_move) in [(7, Move::Pop(9)), (8, Move::Capture(6)), (9, Move::Return(9, 0))] {
+            let entry = TableEntry { best_move: Some(best_move), ..stored };
+            tt.store(key, entry);
+            assert_eq!(tt.probe(key), Some(entry));
+        }
+    }
+
+    #[test]
+    fn depth_preferred_keeps_deeper_entry() {
+        let tt = TranspositionTable::with_policy(16, ReplacementPolicy::DepthPreferred);
+        tt.store(1, entry(5, 10));
+        tt.store(17, entry(6, 2)); // same slot, shallower
+        assert_eq!(tt.probe(1), Some(entry(5, 10)));
+        assert_eq!(tt.probe(17), None);
+        assert_eq!(tt.stats().rejected, 1);
+
+        tt.store(1, entry(7, 1)); // same position always updates
+        assert_eq!(tt.probe(1), Some(entry(7, 1)));
+    }
+
+    #[test]
+    fn always_replace_overwrites() {
+        let tt = TranspositionTable::with_policy(16, ReplacementPolicy::AlwaysReplace);
+        tt.store(1, entry(5, 10));
+        tt.store(17, entry(6, 2));
+        assert_eq!(tt.probe(1), None);
+        assert_eq!(tt.probe(17), Some(entry(6, 2)));
+        assert_eq!(tt.stats().overwrites, 1);
+
+        tt.clear();
+        assert!(tt.is_empty());
+        assert_eq!(tt.stats(), TableStats::default());
+    }
+}
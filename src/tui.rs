@@ -0,0 +1,604 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::board::Cell;
+use crate::clock::{format_time, Clock};
+use crate::engine::{is_decisive, Engine, SearchResult};
+use crate::game::{Game, GameStatus};
+use crate::player::{Player, PlayerAction, PlayerType};
+use crate::variant::{Move, Variant, POP10_TARGET};
+
+const TICK: Duration = Duration::from_millis(40);
+const EVALUATION_DEPTH: u8 = 6;
+const PLAYER_COLORS: [Color; 4] = [Color::LightRed, Color::LightYellow, Color::LightGreen, Color::LightBlue];
+
+/// A disc falling towards the cell the game already placed it in.
+#[derive(Debug, Clone)]
+struct Animation {
+    col: u8,
+    row: u8,
+    target_row: u8,
+    player: Player
+}
+
+/// Full-screen front end state, every rule is delegated to the wrapped `Game`.
+#[derive(Debug)]
+pub struct App {
+    game: Game,
+    cursor: u8,
+    animation: Option<Animation>,
+    evaluation: Option<SearchResult>,
+    hint: Option<u8>,
+    returning: Option<u8>, // Pop 10: column of a popped disc waiting to be dropped back
+    pending_evaluation: Option<Receiver<SearchResult>>,
+    pending_action: Option<Receiver<Result<PlayerAction, String>>>, // a computer player still thinking
+    message: String,
+    quit: bool
+}
+
+
+fn player_color(player: &Player) -> Color {
+    PLAYER_COLORS[(player.id as usize + PLAYER_COLORS.len() - 1) % PLAYER_COLORS.len()]
+}
+
+
+/// Runs `search` on its own thread so the screen keeps redrawing, its result arrives on the receiver.
+fn spawn_search<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(search: F) -> Receiver<T> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(search()); // the app may have moved on and dropped the receiver
+    });
+    receiver
+}
+
+
+impl App {
+    pub fn new(game: Game) -> Self {
+        let mut app = Self {
+            cursor: game.board().get_cols() / 2,
+            game,
+            animation: None,
+            evaluation: None,
+            hint: None,
+            returning: None,
+            pending_evaluation: None,
+            pending_action: None,
+            message: String::from("Welcome to connect four!"),
+            quit: false
+        };
+        app.evaluate();
+        app
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn is_human_turn(&self) -> bool {
+        self.game.current_player().player_type == PlayerType::HUMAN
+    }
+
+    fn current_index(&self) -> usize {
+        let current = self.game.current_player();
+        self.game.players().iter().position(|player| *player == current).unwrap_or(0)
+    }
+
+    /// Starts evaluating the position in the background, forgetting any evaluation of an earlier one.
+    fn evaluate(&mut self) {
+        self.evaluation = None;
+        self.pending_evaluation = None;
+        if self.game.status() == GameStatus::InProgress {
+            let board = self.game.board().clone();
+            let players = self.game.active_players();
+            let current = self.game.current_player();
+            let player_index = players.iter().position(|active| *active == current).unwrap_or(0);
+            self.pending_evaluation = Some(spawn_search(move || Engine::new(EVALUATION_DEPTH).search(&board, &players, player_index)));
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('n') => self.new_game(),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('h') => self.show_hint(),
+            _ if self.animation.is_some() || !self.is_human_turn() => {},
+            KeyCode::Left => self.move_cursor(-1),
+            KeyCode::Right => self.move_cursor(1),
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                let col = c.to_digit(10).unwrap() as u8;
+                if col < self.game.board().get_cols() {
+                    self.cursor = col;
+                }
+            },
+            KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Down => match self.returning.take() {
+                Some(from) => self.play(Move::Return(from, self.cursor)),
+                None => self.play(Move::Drop(self.cursor))
+            },
+            KeyCode::Char('p') | KeyCode::Up => self.pop(),
+            KeyCode::Char('r') => self.act(PlayerAction::Resign),
+            KeyCode::Char('d') => self.act(PlayerAction::OfferDraw),
+            KeyCode::Char('a') => self.act(PlayerAction::Abort),
+            _ => {}
+        }
+    }
+
+    /// Moves the cursor `step` columns, around the edge of a cylinder and up to it otherwise.
+    fn move_cursor(&mut self, step: i16) {
+        if let Some((_, col)) = self.game.board().cell_at(0, self.cursor as i16 + step) {
+            self.cursor = col;
+        }
+    }
+
+    /// Pops the disc under the cursor, in Pop 10 a disc outside a line then waits for a column to go back into.
+    fn pop(&mut self) {
+        let board = self.game.board();
+        let player = self.game.current_player();
+        if !board.is_popping() {
+            self.play(Move::Pop(self.cursor));
+        } else if board.is_legal(Move::Capture(self.cursor), &player) {
+            self.play(Move::Capture(self.cursor));
+        } else if board.is_legal(Move::Return(self.cursor, self.cursor), &player) {
+            self.returning = Some(self.cursor);
+            self.message = format!("Pick the column to drop the disc from column {} into", self.cursor);
+        } else {
+            self.message = format!("Cannot pop column {}, only your own bottom discs can be popped", self.cursor);
+        }
+    }
+
+    fn play(&mut self, mv: Move) {
+        let player = self.game.current_player();
+        match self.game.make_move(mv) {
+            Ok(status) => {
+                self.hint = None;
+                let (target_row, col) = self.game.last_move().unwrap();
+                if let Move::Drop(_) | Move::Return(..) = mv {
+                    self.animation = Some(Animation { col, row: 0, target_row, player: player.clone() });
+                }
+                self.message = match (status, mv) {
+                    (GameStatus::InProgress, Move::Drop(col)) => format!("{} played column {}", player.name, col),
+                    (GameStatus::InProgress, Move::Pop(col)) => format!("{} popped column {}", player.name, col),
+                    (GameStatus::InProgress, Move::Capture(col)) => format!("{} captured the disc in column {}", player.name, col),
+                    (GameStatus::InProgress, Move::Return(from, to)) => {
+                        format!("{} popped column {} and dropped the disc into column {}", player.name, from, to)
+                    },
+                    (GameStatus::Won(winner), _) => format!("Player {} won! Press n for a new game", winner.name),
+                    (GameStatus::Draw, _) => String::from("It's a draw! Press n for a new game"),
+                    (GameStatus::Aborted, _) => String::from("Game aborted! Press n for a new game")
+                };
+                self.evaluate();
+            },
+            Err(e) => self.message = e
+        }
+    }
+
+    fn undo(&mut self) {
+        if self.game.undo_move().is_none() {
+            self.message = String::from("Nothing to undo");
+            return;
+        }
+        // take back computer replies too, so it is a human's turn again
+        let has_human = self.game.players().iter().any(|player| player.player_type == PlayerType::HUMAN);
+        while has_human && !self.is_human_turn() && self.game.undo_move().is_some() {}
+        self.animation = None;
+        self.hint = None;
+        self.returning = None;
+        self.pending_action = None;
+        self.message = String::from("Move taken back");
+        self.evaluate();
+    }
+
+    fn show_hint(&mut self) {
+        if self.evaluation.is_none() && self.pending_evaluation.is_some() {
+            self.message = String::from("Still thinking, ask again in a moment");
+        }
+        if let Some(best_move) = self.evaluation.and_then(|evaluation| evaluation.best_move) {
+            self.hint = Some(best_move.col());
+            self.cursor = best_move.col();
+            self.message = match best_move {
+                Move::Drop(col) => format!("Hint: column {}", col),
+                Move::Pop(col) => format!("Hint: pop column {}", col),
+                Move::Capture(col) => format!("Hint: capture column {}", col),
+                Move::Return(from, to) => format!("Hint: pop column {} and drop into column {}", from, to)
+            };
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.game.restart();
+        self.animation = None;
+        self.hint = None;
+        self.returning = None;
+        self.pending_action = None;
+        self.message = match self.game.start_engines() {
+            Ok(()) => String::from("New game started"),
+            Err(e) => e
+        };
+        self.evaluate();
+    }
+
+    /// Picks up finished searches, checks the clock, advances the drop animation and lets computer players
+    /// move once it has landed.
+    pub fn tick(&mut self) {
+        if let Some(Ok(evaluation)) = self.pending_evaluation.as_ref().map(Receiver::try_recv) {
+            self.evaluation = Some(evaluation);
+            self.pending_evaluation = None;
+        }
+        let player = self.game.current_player();
+        if self.game.clock().and_then(Clock::flagged).is_some() {
+            self.game.check_clock();
+            self.pending_action = None;
+            self.message = format!("{} ran out of time", player.name);
+            self.evaluate();
+        }
+        if let Some(animation) = self.animation.as_mut() {
+            animation.row += 1;
+            if animation.row >= animation.target_row {
+                self.animation = None;
+            }
+            return;
+        }
+        if self.game.status() != GameStatus::InProgress || self.is_human_turn() {
+            return;
+        }
+        let Some(pending) = self.pending_action.as_ref() else {
+            self.pending_action = Some(self.think());
+            return;
+        };
+        let action = match pending.try_recv() {
+            Ok(action) => action,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(format!("{} stopped thinking", player.name))
+        };
+        self.pending_action = None;
+        match action {
+            Ok(action) => self.act(action),
+            Err(e) => {
+                self.message = e;
+                self.evaluate();
+            }
+        }
+    }
+
+    /// Carries out `action` for the current player, resigning, offering or accepting a draw and aborting included.
+    fn act(&mut self, action: PlayerAction) {
+        let player = self.game.current_player();
+        let player_index = self.game.current_player_index();
+        let result = match action {
+            PlayerAction::Resign => self.game.resign(player_index).map(|_| format!("{} resigned", player.name)),
+            PlayerAction::OfferDraw => self.game.offer_draw(player_index).map(|status| match status {
+                GameStatus::Draw => String::from("Draw agreed! Press n for a new game"),
+                _ => format!("{} offers a draw", player.name)
+            }),
+            PlayerAction::Abort => self.game.abort(player_index).map(|_| format!("{} aborted the game", player.name)),
+            action => {
+                self.play(action.to_move().unwrap());
+                return;
+            }
+        };
+        self.message = result.unwrap_or_else(|e| e);
+        self.evaluate();
+    }
+
+    /// Starts the current computer player's search on another thread, timed by the game's clock.
+    fn think(&self) -> Receiver<Result<PlayerAction, String>> {
+        let player = self.game.current_player();
+        let board = self.game.board().clone();
+        let players = self.game.active_players();
+        let limits = self.game.go_limits();
+        let player_index = players.iter().position(|active| *active == player).unwrap_or(0);
+        let engine = self.game.engine(self.game.current_player_index());
+        spawn_search(move || match player.player_type {
+            PlayerType::AI(depth) => player
+                .generate_best_move(&board, &players, depth, limits.time_budget(player_index))
+                .map(PlayerAction::from),
+            PlayerType::ENGINE(ref command) => engine
+                .ok_or(format!("Engine {} was not started", command))?
+                .lock()
+                .map_err(|e| e.to_string())?
+                .best_move(&board, &players, &player, &limits, false)
+                .map(|(action, _)| action),
+            _ => player.generate_move(&board).map(PlayerAction::from)
+        })
+    }
+
+    fn board_lines(&self) -> Vec<Line<'static>> {
+        let board = self.game.board();
+        let winning_cells = self.game.winning_cells();
+        let mut lines = vec![];
+
+        let hover = (0..board.get_cols())
+            .map(|col| {
+                if self.animation.is_none() && self.is_human_turn() && col == self.cursor
+                    && self.game.status() == GameStatus::InProgress {
+                    Span::styled(" ● ", Style::default().fg(player_color(&self.game.current_player())))
+                } else {
+                    Span::raw("   ")
+                }
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from([vec![Span::raw(" ")], hover].concat()));
+
+        for row in 0..board.get_rows() {
+            let mut spans = vec![Span::styled("│", Style::default().fg(Color::Blue))];
+            for col in 0..board.get_cols() {
+                let cell = (row, col);
+                let falling = self.animation.as_ref().filter(|animation| animation.col == col);
+                let shown = match falling {
+                    Some(animation) if animation.row == row => Cell::Disc(animation.player.clone()),
+                    Some(animation) if animation.target_row == row => Cell::Empty,
+                    _ => board.cell(row, col).clone()
+                };
+                let span = match shown {
+                    Cell::Disc(player) => {
+                        let mut style = Style::default().fg(player_color(&player));
+                        if winning_cells.contains(&cell) {
+                            style = style.add_modifier(Modifier::REVERSED);
+                        } else if self.game.last_move() == Some(cell) && falling.is_none() {
+                            style = style.add_modifier(Modifier::BOLD);
+                        }
+                        Span::styled(" ● ", style)
+                    },
+                    Cell::Blocked => Span::styled(" ■ ", Style::default().fg(Color::Gray)),
+                    Cell::Empty => Span::styled(" · ", Style::default().fg(Color::DarkGray))
+                };
+                spans.push(span);
+            }
+            spans.push(Span::styled("│", Style::default().fg(Color::Blue)));
+            lines.push(Line::from(spans));
+        }
+
+        let footer = (0..board.get_cols())
+            .map(|col| {
+                let style = if self.hint == Some(col) {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                Span::styled(format!(" {} ", col), style)
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from([vec![Span::raw(" ")], footer].concat()));
+        lines
+    }
+
+    fn side_panel_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(Span::styled("Players", Style::default().add_modifier(Modifier::BOLD)))];
+        let current_index = self.current_index();
+        for (index, player) in self.game.players().iter().enumerate() {
+            let clock = match self.game.clock() {
+                Some(clock) => format!(" {:>6}", format_time(clock.time_left(index))),
+                None => String::new()
+            };
+            let turn_marker = if index == current_index { "▶" } else { " " };
+            let captured = match self.game.board().variant() {
+                Variant::Pop10 => format!(" {:>2}/{}", self.game.board().captured(player), POP10_TARGET),
+                _ => String::new()
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("{} ", turn_marker)),
+                Span::styled("● ", Style::default().fg(player_color(player))),
+                Span::raw(format!("{:<10}{}{}", player.name, clock, captured)),
+            ]));
+        }
+
+        lines.push(Line::raw(""));
+        let evaluation = match self.evaluation {
+            Some(evaluation) if is_decisive(evaluation.score) => {
+                let winner = if evaluation.score > 0 { self.game.current_player() } else {
+                    self.game.players()[(current_index + 1) % self.game.players().len()].clone()
+                };
+                format!("{} wins by force", winner.name)
+            },
+            Some(evaluation) => format!("{:+} for {}", evaluation.score, self.game.current_player().name),
+            None => String::from("-")
+        };
+        lines.push(Line::from(vec![Span::styled("Evaluation ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(evaluation)]));
+
+        lines.push(Line::raw(""));
+        lines.push(Line::from(Span::styled("Moves", Style::default().add_modifier(Modifier::BOLD))));
+        let players = self.game.players().len();
+        for (number, round) in self.game.moves().chunks(players).enumerate() {
+            let cols: Vec<String> = round.iter().map(|col| col.to_string()).collect();
+            lines.push(Line::raw(format!("{:>3}. {}", number + 1, cols.join(" "))));
+        }
+        lines
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [main, status] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .areas(frame.area());
+        let board_width = self.game.board().get_cols() as u16 * 3 + 4;
+        let [board_area, side_area] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(board_width), Constraint::Min(20)])
+            .areas(main);
+
+        frame.render_widget(
+            Paragraph::new(self.board_lines()).block(Block::default().borders(Borders::ALL).title(" Connect four ")),
+            board_area);
+        frame.render_widget(
+            Paragraph::new(self.side_panel_lines()).block(Block::default().borders(Borders::ALL)).wrap(Wrap { trim: true }),
+            side_area);
+        let keys = match self.game.board().variant() {
+            Variant::PopOut => "←/→ or 0-9 pick · enter drop · p pop · u undo · h hint · r resign · d draw · a abort · n new game · q quit",
+            Variant::Pop10 if self.game.board().is_popping() => {
+                "←/→ or 0-9 pick · p pop · enter drop a popped disc · u undo · h hint · r resign · d draw · a abort · n new game · q quit"
+            },
+            _ => "←/→ or 0-9 pick · enter drop · u undo · h hint · r resign · d draw · a abort · n new game · q quit"
+        };
+        frame.render_widget(
+            Paragraph::new(vec![Line::raw(self.message.clone()), Line::styled(keys, Style::default().fg(Color::DarkGray))])
+                .block(Block::default().borders(Borders::TOP)),
+            Rect { height: status.height.min(3), ..status });
+    }
+}
+
+
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    while !app.should_quit() {
+        terminal.draw(|frame| app.draw(frame))?;
+        if event::poll(TICK)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press {
+            app.handle_key(key.code);
+        }
+        app.tick();
+    }
+    Ok(())
+}
+
+
+/// Runs `game` full screen until the user quits.
+pub fn run_tui(mut game: Game) -> Result<(), String> {
+    game.start_engines()?;
+    enable_raw_mode().map_err(|e| e.to_string())?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen).map_err(|e| e.to_string())?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout)).map_err(|e| e.to_string())?;
+
+    let mut app = App::new(game);
+    let result = event_loop(&mut terminal, &mut app);
+
+    disable_raw_mode().map_err(|e| e.to_string())?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(|e| e.to_string())?;
+    terminal.show_cursor().map_err(|e| e.to_string())?;
+    result.map_err(|e| e.to_string())
+}
+
+
+#[cfg(test)]
+mod tui_tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use crate::board::Board;
+
+    fn human_game() -> Game {
+        Game::new(
+            Board::new(),
+            vec![
+                Player::new(1, "one", PlayerType::HUMAN, 'x'),
+                Player::new(2, "two", PlayerType::HUMAN, 'o'),
+            ],
+            Game::setup_win_condition_strategies()
+        )
+    }
+
+    fn finish_animation(app: &mut App) {
+        while app.animation.is_some() {
+            app.tick();
+        }
+    }
+
+    fn wait_for_computer(app: &mut App) {
+        while !app.is_human_turn() || app.animation.is_some() {
+            app.tick();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn keys_pick_and_drop_columns() {
+        let mut app = App::new(human_game());
+        app.handle_key(KeyCode::Left);
+        app.handle_key(KeyCode::Enter);
+        finish_animation(&mut app);
+        app.handle_key(KeyCode::Char('5'));
+        app.handle_key(KeyCode::Char(' '));
+        assert_eq!(app.game().moves(), [2, 5].map(Move::Drop));
+
+        // input is ignored while a disc is still falling
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.game().moves(), [2, 5].map(Move::Drop));
+
+        app.handle_key(KeyCode::Char('u'));
+        assert_eq!(app.game().moves(), [Move::Drop(2)]);
+        app.handle_key(KeyCode::Char('n'));
+        assert!(app.game().moves().is_empty());
+        app.handle_key(KeyCode::Char('q'));
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn keys_resign_offer_draws_and_abort() {
+        let mut app = App::new(human_game());
+        app.handle_key(KeyCode::Char('d'));
+        app.handle_key(KeyCode::Enter);
+        finish_animation(&mut app);
+        app.handle_key(KeyCode::Char('d'));
+        assert_eq!(app.game().status(), GameStatus::Draw);
+
+        app.handle_key(KeyCode::Char('n'));
+        app.handle_key(KeyCode::Char('r'));
+        assert_eq!(app.game().status(), GameStatus::Won(app.game().players()[1].clone()));
+
+        app.handle_key(KeyCode::Char('n'));
+        app.handle_key(KeyCode::Char('a'));
+        assert_eq!(app.game().status(), GameStatus::Aborted);
+    }
+
+    #[test]
+    fn undo_takes_back_computer_reply() {
+        let game = Game::new(
+            Board::new(),
+            vec![
+                Player::new(1, "one", PlayerType::HUMAN, 'x'),
+                Player::new(2, "two", PlayerType::COMPUTER, 'o'),
+            ],
+            Game::setup_win_condition_strategies()
+        );
+        let mut app = App::new(game);
+        app.handle_key(KeyCode::Enter);
+        finish_animation(&mut app);
+        wait_for_computer(&mut app);
+        assert_eq!(app.game().moves().len(), 2);
+        app.handle_key(KeyCode::Char('u'));
+        assert!(app.game().moves().is_empty());
+    }
+
+    #[test]
+    fn draws_board_and_side_panel() {
+        let mut app = App::new(human_game());
+        app.handle_key(KeyCode::Enter);
+        finish_animation(&mut app);
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let content: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(content.contains("Connect four"));
+        assert!(content.contains("Moves"));
+        assert!(content.contains("1. 3"));
+        assert!(content.contains("one played column 3"));
+    }
+
+    #[test]
+    fn side_panel_shows_the_game_clock() {
+        let mut game = human_game();
+        game.set_time_control(Some(crate::clock::TimeControl::parse("300+5").unwrap()));
+        let mut app = App::new(game);
+        app.handle_key(KeyCode::Enter);
+        finish_animation(&mut app);
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let content: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(content.contains("5:04") && content.contains("4:59"));
+    }
+}
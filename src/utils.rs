@@ -1,11 +1,39 @@
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
 
-use std::io::{stdin, stdout, Write};
+/// Lines of stdin, read on a background thread so reads can time out.
+fn stdin_lines() -> &'static Mutex<Receiver<String>> {
+    static LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
 
 pub fn get_cli_input() -> String {
     std::io::stdout().flush().unwrap();
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).expect("Failed to read input");
-    input
+    stdin_lines().lock().unwrap().recv().unwrap_or_default()
+}
+
+/// Like `get_cli_input`, `None` when nothing was entered within `timeout`.
+pub fn get_cli_input_timeout(timeout: Duration) -> Option<String> {
+    std::io::stdout().flush().unwrap();
+    match stdin_lines().lock().unwrap().recv_timeout(timeout) {
+        Ok(line) => Some(line),
+        Err(RecvTimeoutError::Timeout) => None,
+        Err(RecvTimeoutError::Disconnected) => Some(String::new())
+    }
 }
 
 pub fn cli_confirms() -> bool {
@@ -0,0 +1,132 @@
+use std::fmt;
+
+use crate::win_condition_strategy::StrategyOutcome;
+
+/// Discs a player has to collect to win Pop 10.
+pub const POP10_TARGET: u8 = 10;
+
+/// Rule set deciding which moves a player may make.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Variant {
+    #[default]
+    Standard,
+    PopOut, // a player may also pop one of their own discs out of the bottom row
+    Pop10, // the board is filled, then players pop their own discs and collect the ones in a line
+    Misere // completing a line loses
+}
+
+/// Dropping a disc into a column, or popping the bottom disc out of it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Move {
+    Drop(u8),
+    Pop(u8),
+    Capture(u8), // Pop 10: pops a disc that is part of a line and keeps it
+    Return(u8, u8) // Pop 10: pops a disc that is not part of a line and drops it into the second column
+}
+
+
+impl Variant {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            "standard" => Ok(Variant::Standard),
+            "popout" => Ok(Variant::PopOut),
+            "pop10" => Ok(Variant::Pop10),
+            "misere" => Ok(Variant::Misere),
+            other => Err(format!("Unknown variant \"{}\", expected standard, popout, pop10 or misere", other))
+        }
+    }
+
+    pub fn allows_pop(&self) -> bool {
+        matches!(self, Variant::PopOut)
+    }
+
+    /// What completing a line means for its player, `None` in Pop 10 where lines only earn discs.
+    pub fn line_outcome(&self) -> Option<StrategyOutcome> {
+        match self {
+            Variant::Standard | Variant::PopOut => Some(StrategyOutcome::Win),
+            Variant::Pop10 => None,
+            Variant::Misere => Some(StrategyOutcome::Loss)
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::PopOut => write!(f, "popout"),
+            Variant::Pop10 => write!(f, "pop10"),
+            Variant::Misere => write!(f, "misere")
+        }
+    }
+}
+
+
+impl Move {
+    /// A column number for a drop, `p` and a column number for a pop, `c` and a column number for a capture,
+    /// `p<column>><column>` for a return.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let column = |col: &str| col.parse::<u8>().map_err(|e| format!("Invalid move \"{}\": {}", text, e));
+        if let Some(col) = text.strip_prefix('c') {
+            return Ok(Move::Capture(column(col)?));
+        }
+        match text.strip_prefix('p') {
+            Some(pop) => match pop.split_once('>') {
+                Some((from, to)) => Ok(Move::Return(column(from)?, column(to)?)),
+                None => Ok(Move::Pop(column(pop)?))
+            },
+            None => Ok(Move::Drop(column(text)?))
+        }
+    }
+
+    /// Column the disc is dropped into or popped from.
+    pub fn col(&self) -> u8 {
+        match self {
+            Move::Drop(col) | Move::Pop(col) | Move::Capture(col) | Move::Return(col, _) => *col
+        }
+    }
+
+    /// The same kind of move with every column mapped by `f`.
+    pub fn map_cols<F: Fn(u8) -> u8>(&self, f: F) -> Self {
+        match self {
+            Move::Drop(col) => Move::Drop(f(*col)),
+            Move::Pop(col) => Move::Pop(f(*col)),
+            Move::Capture(col) => Move::Capture(f(*col)),
+            Move::Return(from, to) => Move::Return(f(*from), f(*to))
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::Drop(col) => write!(f, "{}", col),
+            Move::Pop(col) => write!(f, "p{}", col),
+            Move::Capture(col) => write!(f, "c{}", col),
+            Move::Return(from, to) => write!(f, "p{}>{}", from, to)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod variant_tests {
+    use super::*;
+
+    #[test]
+    fn moves_and_variants_parse_their_display() {
+        for mv in [Move::Drop(3), Move::Pop(0), Move::Capture(5), Move::Return(2, 6)] {
+            assert_eq!(Move::parse(&mv.to_string()), Ok(mv));
+        }
+        assert_eq!(Move::parse(" p6\n").map(|mv| mv.col()), Ok(6));
+        assert!(Move::parse("pp1").is_err());
+        assert!(Move::parse("p1>").is_err());
+        assert_eq!(Move::Return(1, 5).map_cols(|col| 6 - col), Move::Return(5, 1));
+        assert_eq!(Variant::parse("popout"), Ok(Variant::PopOut));
+        assert_eq!(Variant::parse("pop10").unwrap().line_outcome(), None);
+        assert_eq!(Variant::parse("misere").unwrap().line_outcome(), Some(StrategyOutcome::Loss));
+        assert!(Variant::parse("sideways").is_err());
+        assert!(!Variant::default().allows_pop());
+    }
+}
@@ -0,0 +1,471 @@
+//! Live games over WebSocket, enabled with the `websocket` feature.
+//!
+//! Every frame is a JSON text message with a `type` field. Games are played in named rooms with
+//! two player seats and any number of spectators.
+//!
+//! Client to server:
+//! - `{"type": "join", "room": <room>, "name": <name>, "role": "player" | "spectator"}`
+//! - `{"type": "move", "column": <col>}`: drop a disc, only accepted from the player to move
+//! - `{"type": "resign"}` | `{"type": "draw"}` | `{"type": "abort"}`: give up, offer or accept a draw, or
+//!   end the game without a result
+//! - `{"type": "rematch"}`: vote for a new game in the room once the current one is over, it starts when both
+//!   players voted
+//!
+//! Server to client:
+//! - `{"type": "welcome", "role": ..., "player": <player id or null>}`
+//! - `{"type": "snapshot", "moves": ..., "board": [<row>, ...], "next_player": ..., "status": ..., "players": [...]}`:
+//!   sent on joining and after a reset, so a reconnecting client can rebuild the game
+//! - `{"type": "move", "player": <id>, "row": <row>, "column": <col>, "board": [<row>, ...]}`
+//! - `{"type": "draw_offer", "player": <id>}`: open until the other player moves
+//! - `{"type": "rematch_offer", "player": <id>}`: a player voted for a new game
+//! - `{"type": "game_over", "result": "win", "winner": <id>, "line": [[<row>, <col>], ...], "termination": ...}` |
+//!   `{"type": "game_over", "result": "draw", "termination": ...}` | `{"type": "game_over", "result": "aborted", "player": <id>}`,
+//!   the termination is `normal`, `time forfeit`, `resignation` or `agreement`
+//! - `{"type": "presence", "player": <id>, "connected": <bool>}`: a player left or came back
+//! - `{"type": "error", "message": ...}`
+//!
+//! A player seat belongs to the name that took it, joining again with that name reclaims it.
+
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use tungstenite::{accept, Message, WebSocket};
+
+use crate::board::Board;
+use crate::events::GameEvent;
+use crate::game::{Game, GameStatus};
+use crate::player::{Player, PlayerAction, PlayerType};
+use crate::protocol::board_to_rows;
+use crate::variant::Move;
+
+pub const DEFAULT_WEBSOCKET_ADDRESS: &str = "127.0.0.1:4445";
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug)]
+struct Seat {
+    name: String,
+    client: Option<u64>
+}
+
+#[derive(Debug)]
+struct Room {
+    game: Game,
+    events: Receiver<GameEvent>,
+    seats: Vec<Option<Seat>>,
+    rematch_votes: Vec<bool>,
+    clients: BTreeMap<u64, Sender<String>>
+}
+
+type Rooms = Arc<Mutex<BTreeMap<String, Room>>>;
+
+
+fn board_json(board: &Board) -> Value {
+    json!(board_to_rows(board).split('/').collect::<Vec<_>>())
+}
+
+fn error_json(message: &str) -> Value {
+    json!({ "type": "error", "message": message })
+}
+
+
+impl Room {
+    fn new() -> Self {
+        let players = vec![
+            Player::new(1, "player-one", PlayerType::HUMAN, 'x'),
+            Player::new(2, "player-two", PlayerType::HUMAN, 'o'),
+        ];
+        let seats = players.iter().map(|_| None).collect();
+        let players_count = players.len();
+        let mut game = Game::new(Board::new(), players, Game::setup_win_condition_strategies());
+        let events = game.subscribe();
+        let rematch_votes = vec![false; players_count];
+        Self { game, events, seats, rematch_votes, clients: BTreeMap::new() }
+    }
+
+    fn send_to(&mut self, client: u64, message: &Value) {
+        if let Some(sender) = self.clients.get(&client) {
+            let _ = sender.send(message.to_string());
+        }
+    }
+
+    fn broadcast(&mut self, message: &Value) {
+        let message = message.to_string();
+        self.clients.retain(|_, sender| sender.send(message.clone()).is_ok());
+    }
+
+    fn snapshot(&self) -> Value {
+        let status = self.game.status();
+        json!({
+            "type": "snapshot",
+            "moves": self.game.moves().iter().map(|col| col.to_string()).collect::<String>(),
+            "board": board_json(self.game.board()),
+            "next_player": if status == GameStatus::InProgress { json!(self.game.current_player().id) } else { Value::Null },
+            "status": match status {
+                GameStatus::InProgress => "in_progress",
+                GameStatus::Won(_) => "won",
+                GameStatus::Draw => "draw",
+                GameStatus::Aborted => "aborted"
+            },
+            "draw_offer": self.game.draw_offer().map(|player| player.id),
+            "players": self.seats.iter().zip(self.game.players()).map(|(seat, player)| json!({
+                "id": player.id,
+                "name": seat.as_ref().map(|seat| seat.name.as_str()),
+                "connected": seat.as_ref().is_some_and(|seat| seat.client.is_some())
+            })).collect::<Vec<_>>()
+        })
+    }
+
+    /// Forwards everything the game emitted since the last call to all clients.
+    fn publish_events(&mut self) {
+        let events: Vec<GameEvent> = self.events.try_iter().collect();
+        for event in events {
+            let message = match event {
+                GameEvent::MovePlayed { player, row, col } => json!({
+                    "type": "move", "player": player.id, "row": row, "column": col, "board": board_json(self.game.board())
+                }),
+                GameEvent::Won { player, line } => json!({
+                    "type": "game_over", "result": "win", "winner": player.id, "line": line, "termination": self.termination()
+                }),
+                GameEvent::Draw => json!({ "type": "game_over", "result": "draw", "termination": self.termination() }),
+                GameEvent::DrawOffered { player } => json!({ "type": "draw_offer", "player": player.id }),
+                GameEvent::Aborted { player } => json!({ "type": "game_over", "result": "aborted", "player": player.id }),
+                GameEvent::Reset => self.snapshot(),
+                // rooms play the standard rules, so nothing is ever popped
+                GameEvent::Started | GameEvent::Popped { .. } | GameEvent::Captured { .. } | GameEvent::InvalidMove { .. } | GameEvent::Flagged { .. }
+                | GameEvent::Resigned { .. } | GameEvent::ActionRejected { .. } => continue
+            };
+            self.broadcast(&message);
+        }
+    }
+
+    fn join(&mut self, client: u64, name: &str, role: &str) -> Result<Option<usize>, String> {
+        let seat = match role {
+            "spectator" => None,
+            "player" => {
+                let seat = self.seats
+                    .iter()
+                    .position(|seat| seat.as_ref().is_some_and(|seat| seat.name == name))
+                    .or_else(|| self.seats.iter().position(Option::is_none))
+                    .ok_or(String::from("Both seats are taken"))?;
+                if self.seats[seat].as_ref().is_some_and(|seat| seat.client.is_some()) {
+                    return Err(format!("{} is already connected", name));
+                }
+                self.seats[seat] = Some(Seat { name: name.to_string(), client: Some(client) });
+                Some(seat)
+            },
+            _ => return Err(format!("Unknown role \"{}\"", role))
+        };
+        let player = seat.map(|seat| self.game.players()[seat].id);
+        self.send_to(client, &json!({ "type": "welcome", "role": role, "player": player }));
+        if let Some(player) = player {
+            self.broadcast(&json!({ "type": "presence", "player": player, "connected": true }));
+        }
+        let snapshot = self.snapshot();
+        self.send_to(client, &snapshot);
+        Ok(seat)
+    }
+
+    fn termination(&self) -> Option<String> {
+        self.game.termination().map(|termination| termination.to_string())
+    }
+
+    /// Moves are only accepted on the player's turn, the other actions at any time.
+    fn act(&mut self, seat: usize, action: PlayerAction) -> Result<(), String> {
+        let player = self.game.players()[seat].clone();
+        if self.seats.iter().any(Option::is_none) {
+            return Err(String::from("Waiting for an opponent"));
+        }
+        if self.game.status() != GameStatus::InProgress {
+            return Err(String::from("The game is over"));
+        }
+        let result = match action {
+            PlayerAction::Move(_) | PlayerAction::Pop(_) | PlayerAction::Capture(_) | PlayerAction::Return(..) if self.game.current_player() != player => {
+                return Err(String::from("Not your turn"));
+            },
+            PlayerAction::Move(col) => self.game.play_move(col),
+            PlayerAction::Pop(col) => self.game.make_move(Move::Pop(col)),
+            PlayerAction::Capture(col) => self.game.make_move(Move::Capture(col)),
+            PlayerAction::Return(from, to) => self.game.make_move(Move::Return(from, to)),
+            PlayerAction::Resign => self.game.resign(seat),
+            PlayerAction::OfferDraw => self.game.offer_draw(seat),
+            PlayerAction::Abort => self.game.abort(seat)
+        };
+        self.publish_events();
+        result.map(|_| ())
+    }
+
+    fn leave(&mut self, client: u64) {
+        self.clients.remove(&client);
+        let Some(seat) = self.seats.iter().position(|seat| seat.as_ref().is_some_and(|seat| seat.client == Some(client))) else {
+            return;
+        };
+        if let Some(seat) = self.seats[seat].as_mut() {
+            seat.client = None;
+        }
+        let player = self.game.players()[seat].id;
+        self.broadcast(&json!({ "type": "presence", "player": player, "connected": false }));
+    }
+}
+
+
+/// Hosts any number of rooms for WebSocket clients.
+#[derive(Debug)]
+pub struct WebSocketServer {
+    listener: TcpListener,
+    rooms: Rooms,
+    next_client: Arc<AtomicU64>
+}
+
+impl WebSocketServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| e.to_string())?;
+        Ok(Self { listener, rooms: Arc::new(Mutex::new(BTreeMap::new())), next_client: Arc::new(AtomicU64::new(1)) })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    /// Accepts connections forever, every client is served on its own thread.
+    pub fn run(self) -> Result<(), String> {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let rooms = Arc::clone(&self.rooms);
+            let client = self.next_client.fetch_add(1, Ordering::Relaxed);
+            thread::spawn(move || {
+                if let Ok(socket) = accept(stream) {
+                    handle_client(socket, client, rooms);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+
+fn handle_message(text: &str, client: u64, outgoing: &Sender<String>, joined: &mut Option<(String, Option<usize>)>, rooms: &Rooms) -> Result<(), String> {
+    let message: Value = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let field = |name: &str| message.get(name).and_then(Value::as_str);
+    let mut rooms = rooms.lock().unwrap();
+    match (field("type"), joined.as_ref()) {
+        (Some("join"), None) => {
+            let room_name = field("room").ok_or(String::from("join needs a room"))?;
+            let name = field("name").filter(|name| !name.is_empty()).ok_or(String::from("join needs a name"))?;
+            let room = rooms.entry(room_name.to_string()).or_insert_with(Room::new);
+            room.clients.insert(client, outgoing.clone());
+            match room.join(client, name, field("role").unwrap_or("spectator")) {
+                Ok(seat) => {
+                    *joined = Some((room_name.to_string(), seat));
+                    Ok(())
+                },
+                Err(e) => {
+                    room.clients.remove(&client);
+                    Err(e)
+                }
+            }
+        },
+        (Some("join"), Some(_)) => Err(String::from("Already joined")),
+        (Some(_), None) => Err(String::from("Join a room first")),
+        (Some("move"), Some((room, seat))) => {
+            let seat = seat.ok_or(String::from("Spectators cannot move"))?;
+            let col = message
+                .get("column")
+                .and_then(Value::as_u64)
+                .and_then(|col| u8::try_from(col).ok())
+                .ok_or(String::from("move needs a column"))?;
+            rooms.get_mut(room).ok_or(String::from("Room closed"))?.act(seat, PlayerAction::Move(col))
+        },
+        (Some(action @ ("resign" | "draw" | "abort")), Some((room, seat))) => {
+            let seat = seat.ok_or(format!("Spectators cannot {}", action))?;
+            rooms.get_mut(room).ok_or(String::from("Room closed"))?.act(seat, PlayerAction::parse(action)?)
+        },
+        (Some("rematch"), Some((room, seat))) => {
+            let seat = seat.ok_or(String::from("Spectators cannot start a rematch"))?;
+            let room = rooms.get_mut(room).ok_or(String::from("Room closed"))?;
+            if room.game.status() == GameStatus::InProgress {
+                return Err(String::from("The game is not over"));
+            }
+            room.rematch_votes[seat] = true;
+            if room.rematch_votes.iter().all(|vote| *vote) && room.seats.iter().all(Option::is_some) {
+                room.rematch_votes.iter_mut().for_each(|vote| *vote = false);
+                room.game.restart();
+                room.publish_events();
+            } else {
+                let player = &room.game.players()[seat];
+                room.broadcast(&json!({ "type": "rematch_offer", "player": player.id }));
+            }
+            Ok(())
+        },
+        (other, _) => Err(format!("Unknown message type {:?}", other))
+    }
+}
+
+
+fn handle_client(mut socket: WebSocket<TcpStream>, client: u64, rooms: Rooms) {
+    if socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        return;
+    }
+    let (outgoing, incoming) = channel::<String>();
+    let mut joined: Option<(String, Option<usize>)> = None;
+
+    'connection: loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Err(e) = handle_message(&text, client, &outgoing, &mut joined, &rooms) {
+                    let _ = outgoing.send(error_json(&e).to_string());
+                }
+            },
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(_) => break
+        }
+        for message in incoming.try_iter() {
+            if socket.send(Message::Text(message)).is_err() {
+                break 'connection;
+            }
+        }
+    }
+
+    if let Some((room, _)) = joined
+        && let Some(room) = rooms.lock().unwrap().get_mut(&room) {
+        room.leave(client);
+    }
+}
+
+
+/// Hosts rooms on `addr` until the process is stopped.
+pub fn run_websocket_server(addr: &str) -> Result<(), String> {
+    let server = WebSocketServer::bind(addr)?;
+    println!("Hosting live games on ws://{}", server.local_addr()?);
+    server.run()
+}
+
+
+#[cfg(test)]
+mod websocket_tests {
+    use super::*;
+    use tungstenite::connect;
+    use tungstenite::stream::MaybeTlsStream;
+
+    type Client = WebSocket<MaybeTlsStream<TcpStream>>;
+
+    fn start_server() -> SocketAddr {
+        let server = WebSocketServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    fn join(addr: SocketAddr, name: &str, role: &str) -> Client {
+        let (mut client, _) = connect(format!("ws://{}/", addr)).unwrap();
+        send(&mut client, json!({ "type": "join", "room": "lobby", "name": name, "role": role }));
+        client
+    }
+
+    fn send(client: &mut Client, message: Value) {
+        client.send(Message::Text(message.to_string())).unwrap();
+    }
+
+    fn receive(client: &mut Client) -> Value {
+        loop {
+            if let Message::Text(text) = client.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Skips messages until one of the given type arrives.
+    fn receive_type(client: &mut Client, message_type: &str) -> Value {
+        loop {
+            let message = receive(client);
+            if message["type"] == message_type {
+                return message;
+            }
+        }
+    }
+
+    #[test]
+    fn players_and_spectators_follow_the_game() {
+        let addr = start_server();
+        let mut one = join(addr, "alice", "player");
+        assert_eq!(receive(&mut one), json!({ "type": "welcome", "role": "player", "player": 1 }));
+        let mut two = join(addr, "bob", "player");
+        assert_eq!(receive_type(&mut two, "welcome")["player"], 2);
+        let mut spectator = join(addr, "carol", "spectator");
+        assert_eq!(receive_type(&mut spectator, "snapshot")["status"], "in_progress");
+
+        send(&mut two, json!({ "type": "move", "column": 3 }));
+        assert_eq!(receive_type(&mut two, "error")["message"], "Not your turn");
+
+        for (col, player) in [(0, 1), (1, 2), (0, 1), (1, 2), (0, 1), (1, 2)] {
+            let mover = if player == 1 { &mut one } else { &mut two };
+            send(mover, json!({ "type": "move", "column": col }));
+            let moved = receive_type(&mut spectator, "move");
+            assert_eq!(moved["player"], player);
+            assert_eq!(moved["column"], col);
+        }
+        send(&mut one, json!({ "type": "move", "column": 0 }));
+        let game_over = receive_type(&mut spectator, "game_over");
+        assert_eq!(game_over["winner"], 1);
+        assert_eq!(game_over["line"].as_array().unwrap().len(), 4);
+        assert_eq!(receive_type(&mut two, "game_over")["result"], "win");
+        assert_eq!(game_over["termination"], "normal");
+    }
+
+    #[test]
+    fn players_can_agree_a_draw_and_resign() {
+        let addr = start_server();
+        let mut one = join(addr, "alice", "player");
+        let mut two = join(addr, "bob", "player");
+        receive_type(&mut two, "snapshot");
+        send(&mut two, json!({ "type": "draw" }));
+        assert_eq!(receive_type(&mut one, "draw_offer")["player"], 2);
+        send(&mut one, json!({ "type": "draw" }));
+        let game_over = receive_type(&mut two, "game_over");
+        assert_eq!((&game_over["result"], &game_over["termination"]), (&json!("draw"), &json!("agreement")));
+
+        receive_type(&mut one, "game_over");
+        send(&mut one, json!({ "type": "rematch" }));
+        // a single vote does not reset the room
+        assert_eq!(receive_type(&mut two, "rematch_offer")["player"], 1);
+        send(&mut two, json!({ "type": "move", "column": 3 }));
+        assert_eq!(receive_type(&mut two, "error")["message"], "The game is over");
+        send(&mut two, json!({ "type": "rematch" }));
+        receive_type(&mut two, "snapshot");
+        send(&mut two, json!({ "type": "resign" }));
+        let game_over = receive_type(&mut one, "game_over");
+        assert_eq!((&game_over["winner"], &game_over["termination"]), (&json!(1), &json!("resignation")));
+    }
+
+    #[test]
+    fn reconnecting_player_gets_the_current_state() {
+        let addr = start_server();
+        let mut one = join(addr, "alice", "player");
+        let mut two = join(addr, "bob", "player");
+        receive_type(&mut one, "snapshot");
+        send(&mut one, json!({ "type": "move", "column": 4 }));
+        receive_type(&mut two, "move");
+
+        two.close(None).unwrap();
+        let left = json!({ "type": "presence", "player": 2, "connected": false });
+        while receive_type(&mut one, "presence") != left {}
+
+        let mut intruder = join(addr, "mallory", "player");
+        assert_eq!(receive_type(&mut intruder, "error")["message"], "Both seats are taken");
+
+        let mut two = join(addr, "bob", "player");
+        assert_eq!(receive_type(&mut two, "welcome")["player"], 2);
+        let snapshot = receive_type(&mut two, "snapshot");
+        assert_eq!(snapshot["moves"], "4");
+        assert_eq!(snapshot["next_player"], 2);
+        assert_eq!(snapshot["board"][5], "....1..");
+        assert_eq!(receive_type(&mut one, "presence")["connected"], true);
+    }
+}
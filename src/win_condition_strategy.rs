@@ -1,38 +1,140 @@
-use rayon::prelude::*;
-
 use crate::player::Player;
-use crate::board::Board;
+use crate::board::{Board, Topology};
+use crate::variant::{Variant, POP10_TARGET};
+
+/// Default number of discs in a line needed to win, see `Board::win_length`.
+pub const CONNECTED_COMPONENTS_WIN_THRESHOLD: u8 = 4;
 
-const CONNECTED_COMPONENTS_WIN_THRESHOLD: u8 = 4;
+/// What meeting a strategy means for the player who met it, the rule set of the board decides.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StrategyOutcome {
+    Win,
+    Loss
+}
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum WinConditionStrategy {
     HorizontalWinStrategy,
     VerticalWinStrategy,
-    DiagonalWinStrategy
+    DiagonalWinStrategy,
+    CollectionWinStrategy // Pop 10: collecting enough discs
+}
+
+impl StrategyOutcome {
+    /// Index of the winner once `players[index]` of `count` players reached this outcome,
+    /// a loss hands the win to the next player seated after them who `is_active` in the game.
+    pub fn winner<F: Fn(usize) -> bool>(&self, index: usize, count: usize, is_active: F) -> usize {
+        match self {
+            StrategyOutcome::Win => index,
+            StrategyOutcome::Loss => (index + 1..index + count)
+                .map(|seat| seat % count)
+                .find(|seat| is_active(*seat))
+                .unwrap_or(index)
+        }
+    }
 }
 
+
 impl WinConditionStrategy {
-    pub fn has_won(&self, player: &Player, board: &Board) -> bool {
+    /// Whether `player` has what this strategy looks for, whatever the rule set makes of it.
+    pub fn is_met(&self, player: &Player, board: &Board) -> bool {
         match self {
             WinConditionStrategy::HorizontalWinStrategy => has_won_horizontally(player, board),
             WinConditionStrategy::VerticalWinStrategy => has_won_vertically(player, board),
-            WinConditionStrategy::DiagonalWinStrategy => has_won_diagonally(player, board) 
+            WinConditionStrategy::DiagonalWinStrategy => has_won_diagonally(player, board),
+            WinConditionStrategy::CollectionWinStrategy => board.captured(player) >= POP10_TARGET
+        }
+    }
+
+    /// What the rule set of `board` makes of `player` meeting this strategy, `None` while they have not
+    /// or when the strategy does not count in this rule set.
+    pub fn outcome(&self, player: &Player, board: &Board) -> Option<StrategyOutcome> {
+        let outcome = match self {
+            WinConditionStrategy::CollectionWinStrategy => (board.variant() == Variant::Pop10).then_some(StrategyOutcome::Win),
+            _ => board.variant().line_outcome()
+        }?;
+        self.is_met(player, board).then_some(outcome)
+    }
+
+    pub fn has_won(&self, player: &Player, board: &Board) -> bool {
+        self.outcome(player, board) == Some(StrategyOutcome::Win)
+    }
+
+    pub fn has_lost(&self, player: &Player, board: &Board) -> bool {
+        self.outcome(player, board) == Some(StrategyOutcome::Loss)
+    }
+
+    /// Cells (row, col) of the first line this strategy finds for `player`, if lines count in this rule set.
+    pub fn winning_line(&self, player: &Player, board: &Board) -> Option<Vec<(u8, u8)>> {
+        board.variant().line_outcome()?;
+        match self {
+            WinConditionStrategy::HorizontalWinStrategy => find_line(player, board, (0, 1)),
+            WinConditionStrategy::VerticalWinStrategy => find_line(player, board, (1, 0)),
+            WinConditionStrategy::DiagonalWinStrategy => find_line(player, board, (-1, 1))
+                .or_else(|| find_line(player, board, (1, 1))),
+            WinConditionStrategy::CollectionWinStrategy => None
+        }
+    }
+}
+
+
+/// Index of the player `board` is won for, `None` while no strategy decides it. `mover` made the last move and
+/// is checked first, a pop can complete lines for several players at once.
+pub fn decide_winner(strategies: &[WinConditionStrategy], board: &Board, players: &[Player], mover: usize) -> Option<usize> {
+    std::iter::once(mover)
+        .chain((0..players.len()).filter(|index| *index != mover))
+        .find_map(|index| strategies
+            .iter()
+            .find_map(|strategy| strategy.outcome(&players[index], board))
+            .map(|outcome| outcome.winner(index, players.len(), |_| true)))
+}
+
+
+fn find_line(player: &Player, board: &Board, (d_row, d_col): (i16, i16)) -> Option<Vec<(u8, u8)>> {
+    let rows = board.get_rows() as i16;
+    let cols = board.get_cols() as i16;
+    let length = board.win_length() as i16;
+    for row in 0..rows {
+        for col in 0..cols {
+            let line: Vec<(u8, u8)> = (0..length)
+                .map_while(|offset| board.cell_at(row + d_row * offset, col + d_col * offset))
+                .take_while(|(r, c)| board.cell(*r, *c).is_disc_of(player))
+                .collect();
+            if line.len() == length as usize {
+                return Some(line);
+            }
         }
-    }    
+    }
+    None
+}
+
+
+/// Columns a line can start in, every one of them on a cylinder where lines wrap around.
+fn start_cols(board: &Board) -> u8 {
+    match board.topology() {
+        Topology::Flat => board.get_cols() - board.win_length() + 1,
+        Topology::Cylinder => board.get_cols()
+    }
 }
 
 
 fn has_won_horizontally(player: &Player, board: &Board) -> bool {
-    for row in board.data.iter() {
+    let win_length = board.win_length();
+    // on a cylinder the scan runs on into the start of the row to find lines over the edge
+    let scan = match board.topology() {
+        Topology::Flat => board.get_cols(),
+        Topology::Cylinder => board.get_cols() + win_length - 1
+    };
+    for row in 0..board.get_rows() {
         let mut current_count = 0;
         let mut max_count = 0;
         
-        for player_on_board in row.iter() {
-            if player_on_board == player {
+        for col in (0..board.get_cols()).cycle().take(scan as usize) {
+            if board.cell(row, col).is_disc_of(player) {
                 current_count += 1;
                 max_count = std::cmp::max(max_count, current_count);
-                if max_count >= CONNECTED_COMPONENTS_WIN_THRESHOLD {
+                if max_count >= win_length {
                     return true;
                 }
             } else {
@@ -45,15 +147,16 @@ fn has_won_horizontally(player: &Player, board: &Board) -> bool {
 
 
 fn has_won_vertically(player: &Player, board: &Board) -> bool {
+    let win_length = board.win_length();
     for col in 0..board.get_cols() {
         let mut current_count = 0;
         let mut max_count = 0;
 
         for row in 0..board.get_rows() {
-            if board.data[row as usize][col as usize] == *player {
+            if board.cell(row, col).is_disc_of(player) {
                 current_count += 1;
                 max_count = std::cmp::max(max_count, current_count);
-                if max_count >= CONNECTED_COMPONENTS_WIN_THRESHOLD {
+                if max_count >= win_length {
                     return true;
                 }
             } else {
@@ -68,17 +171,18 @@ fn has_won_vertically(player: &Player, board: &Board) -> bool {
 fn has_won_north_east(player: &Player, board: &Board) -> bool {
     let rows = board.get_rows();
     let cols = board.get_cols();
-    for row in (CONNECTED_COMPONENTS_WIN_THRESHOLD-1)..rows {
-        for col in 0..=cols-CONNECTED_COMPONENTS_WIN_THRESHOLD {
+    let win_length = board.win_length();
+    for row in (win_length-1)..rows {
+        for col in 0..start_cols(board) {
             let mut count = 0;
-            for offset in 0..CONNECTED_COMPONENTS_WIN_THRESHOLD {
-                if board.data[(row - offset) as usize][(col + offset) as usize] == *player {
+            for offset in 0..win_length {
+                if board.cell(row - offset, (col + offset) % cols).is_disc_of(player) {
                     count += 1;
                 } else {
                     break;
                 }
             }
-            if count == CONNECTED_COMPONENTS_WIN_THRESHOLD {
+            if count == win_length {
                 return true;
             }
         }
@@ -91,17 +195,18 @@ fn has_won_north_east(player: &Player, board: &Board) -> bool {
 fn has_won_south_east(player: &Player, board: &Board) -> bool {
     let rows = board.get_rows();
     let cols = board.get_cols();
-    for row in 0..=rows-CONNECTED_COMPONENTS_WIN_THRESHOLD {
-        for col in 0..=cols-CONNECTED_COMPONENTS_WIN_THRESHOLD {
+    let win_length = board.win_length();
+    for row in 0..=rows-win_length {
+        for col in 0..start_cols(board) {
             let mut count = 0;
-            for offset in 0..CONNECTED_COMPONENTS_WIN_THRESHOLD { // check 1, 2, 3, 4 on a row etc
-                if board.data[(row + offset) as usize][(col + offset) as usize] == *player {
+            for offset in 0..win_length { // check 1, 2, 3, 4 on a row etc
+                if board.cell(row + offset, (col + offset) % cols).is_disc_of(player) {
                     count += 1;
                 } else {
                     break;
                 }
             }
-            if count == CONNECTED_COMPONENTS_WIN_THRESHOLD {
+            if count == win_length {
                 return true;
             }
         }
@@ -114,6 +219,7 @@ fn has_won_diagonally(player: &Player, board: &Board) -> bool {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::field_reassign_with_default, clippy::useless_vec)]
 mod win_condition_strategy_tests {
     use super::*;
 
@@ -154,6 +260,15 @@ mod win_condition_strategy_tests {
     }
 
 
+    #[test]
+    fn winning_line_is_reported() {
+        let p = Player { marker: 'x', ..Player::default() };
+        let b = Board::generate_diagonal_north_east_win(&p, 4);
+        let line = WinConditionStrategy::DiagonalWinStrategy.winning_line(&p, &b);
+        assert_eq!(line, Some(vec![(5, 0), (4, 1), (3, 2), (2, 3)]));
+        assert_eq!(WinConditionStrategy::HorizontalWinStrategy.winning_line(&p, &b), None);
+    }
+
     #[test]
     fn game_drawn() {
         let b = Board::generate_full_board();
@@ -166,5 +281,72 @@ mod win_condition_strategy_tests {
         p.marker = 'x';
         assert_eq!(all_strategies.iter().any(|strategy| strategy.has_won(&p, &b)), false);
     }
-}
 
+    #[test]
+    fn pop10_is_won_by_collecting() {
+        let p = Player { id: 1, marker: 'x', ..Player::default() };
+        let mut b = Board::generate_horizontal_win(&p, 4);
+        b.set_variant(Variant::Pop10);
+        assert!(!WinConditionStrategy::HorizontalWinStrategy.has_won(&p, &b));
+        assert!(!WinConditionStrategy::CollectionWinStrategy.has_won(&p, &b));
+        b.set_captured(&p, POP10_TARGET);
+        assert!(WinConditionStrategy::CollectionWinStrategy.has_won(&p, &b));
+    }
+
+    #[test]
+    fn misere_lines_lose() {
+        let p = Player { id: 1, marker: 'x', ..Player::default() };
+        let q = Player { id: 2, marker: 'o', ..Player::default() };
+        let mut b = Board::generate_vertical_win(&p, 4);
+        b.set_variant(Variant::Misere);
+        let strategy = WinConditionStrategy::VerticalWinStrategy;
+        assert!(strategy.is_met(&p, &b) && strategy.has_lost(&p, &b) && !strategy.has_won(&p, &b));
+        assert!(strategy.winning_line(&p, &b).is_some());
+        let strategies = [WinConditionStrategy::HorizontalWinStrategy, strategy];
+        assert_eq!(decide_winner(&strategies, &b, &[p.clone(), q.clone()], 0), Some(1));
+        b.set_variant(Variant::Standard);
+        assert_eq!(decide_winner(&strategies, &b, &[p.clone(), q.clone()], 1), Some(0));
+    }
+
+    #[test]
+    fn losses_hand_the_win_to_the_next_active_seat() {
+        assert_eq!(StrategyOutcome::Loss.winner(3, 4, |_| true), 0);
+        assert_eq!(StrategyOutcome::Loss.winner(1, 4, |seat| seat != 2), 3);
+        assert_eq!(StrategyOutcome::Loss.winner(3, 4, |seat| seat == 1), 1);
+        assert_eq!(StrategyOutcome::Win.winner(2, 4, |_| false), 2);
+    }
+
+    #[test]
+    fn cylinder_lines_wrap_around() {
+        let p = Player { id: 1, marker: 'x', ..Player::default() };
+        let mut b = Board::new();
+        for col in [5, 6, 0, 1] {
+            b.set_cell(5, col, &p);
+        }
+        for (row, col) in [(4, 6), (3, 0), (2, 1)] {
+            b.set_cell(row, col, &p);
+        }
+        let horizontal = WinConditionStrategy::HorizontalWinStrategy;
+        let diagonal = WinConditionStrategy::DiagonalWinStrategy;
+        assert!(!horizontal.has_won(&p, &b) && !diagonal.has_won(&p, &b));
+        b.set_topology(Topology::Cylinder);
+        assert!(horizontal.has_won(&p, &b) && diagonal.has_won(&p, &b));
+        assert_eq!(horizontal.winning_line(&p, &b), Some(vec![(5, 5), (5, 6), (5, 0), (5, 1)]));
+        assert_eq!(diagonal.winning_line(&p, &b), Some(vec![(5, 5), (4, 6), (3, 0), (2, 1)]));
+    }
+
+    #[test]
+    fn blockers_break_lines() {
+        let p = Player { id: 1, marker: 'x', ..Player::default() };
+        let mut b = Board::new();
+        b.set_blocked(5, 3).unwrap();
+        for col in [0, 1, 2, 4] {
+            b.set_cell(5, col, &p);
+        }
+        assert!(!WinConditionStrategy::HorizontalWinStrategy.has_won(&p, &b));
+        b.set_topology(Topology::Cylinder);
+        b.set_cell(5, 6, &p);
+        assert!(WinConditionStrategy::HorizontalWinStrategy.has_won(&p, &b));
+        assert!(!b.in_line(5, 4) && b.in_line(5, 0));
+    }
+}